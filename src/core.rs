@@ -1,12 +1,33 @@
 //! Core types and services used by the CLI.
-mod config;
+/// API keys given directly or read from a command or file.
+pub mod apikey;
+/// On-disk response cache.
+pub mod cache;
+pub mod config;
+/// Error type and exit codes.
+pub mod error;
+/// HTTP client settings: proxy, TLS and timeouts.
+pub mod http;
 /// CLI argument definitions.
 pub mod interface;
+/// Conversation messages exchanged with the model.
+pub mod message;
+/// Pluggable chat API backends.
+pub mod provider;
+/// Retry policy for transient backend failures.
+pub mod retry;
 mod router;
+/// JSON Schema validation for structured replies.
+pub mod schema;
+#[allow(dead_code)] // not wired into output yet
 pub mod theme;
+/// Tools the model can call.
+pub mod tool;
 /// Trace server for raw LLM traffic.
 pub mod trace;
 
 pub use config::Config;
-pub use interface::{Cli, Commands};
+pub use error::NeteroError;
+pub use interface::{CacheAction, Cli, Commands};
+pub use message::Message;
 pub use router::Service;
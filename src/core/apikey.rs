@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+/// Where an API key comes from. Commands and files are read on first use only.
+#[derive(Clone, Debug)]
+pub enum ApiKey {
+    /// Key given directly, through an env var or `api_key`.
+    Value(String),
+    /// Shell command printing the key, such as `pass show mistral`.
+    Command(String),
+    /// File holding the key, which must not be readable by other users.
+    File(PathBuf),
+}
+
+/// Failure to obtain an API key; reported as an authentication error.
+#[derive(Debug)]
+pub struct KeyError(pub String);
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for KeyError {}
+
+impl ApiKey {
+    /// Returns the key, running the command or reading the file the first time a given
+    /// one is needed; later calls in the same process reuse the result.
+    ///
+    /// The lock is held while a key is resolved, so racing requests (hedging) wait for
+    /// the first one instead of prompting for the same password twice.
+    pub async fn value(&self) -> Result<String, KeyError> {
+        static RESOLVED: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+        let slot = match self {
+            ApiKey::Value(value) => return Ok(value.clone()),
+            ApiKey::Command(command) => format!("cmd:{}", command),
+            ApiKey::File(path) => format!("file:{}", path.display()),
+        };
+        let mut resolved = RESOLVED.get_or_init(Default::default).lock().await;
+        if let Some(value) = resolved.get(&slot) {
+            return Ok(value.clone());
+        }
+        let value = match self {
+            ApiKey::Command(command) => run(command).await?,
+            ApiKey::File(path) => read_key_file(path)?,
+            ApiKey::Value(value) => value.clone(),
+        };
+        resolved.insert(slot, value.clone());
+        Ok(value)
+    }
+
+    /// Describes the key without revealing it, for `netero config`.
+    pub fn describe(&self) -> String {
+        match self {
+            ApiKey::Value(_) => "<set>".to_string(),
+            ApiKey::Command(command) => format!("<command: {}>", command),
+            ApiKey::File(path) => format!("<file: {}>", path.display()),
+        }
+    }
+}
+
+/// Runs `command` through the shell and returns the first line it prints. Stdin and
+/// stderr stay on the terminal so password prompts (gpg, pass) still work.
+async fn run(command: &str) -> Result<String, KeyError> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let output = Command::new(shell)
+        .arg(flag)
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .await
+        .map_err(|err| KeyError(format!("key command `{}`: {}", command, err)))?;
+    if !output.status.success() {
+        return Err(KeyError(format!(
+            "key command `{}` failed ({})",
+            command, output.status
+        )));
+    }
+    first_line(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| KeyError(format!("key command `{}` printed no key", command)))
+}
+
+/// Reads a key from the first line of `path`, refusing files that group or other users
+/// can access, as ssh does for private keys.
+pub fn read_key_file(path: &Path) -> Result<String, KeyError> {
+    let fail = |message: String| KeyError(format!("key file {}: {}", path.display(), message));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(path)
+            .map_err(|err| fail(err.to_string()))?
+            .permissions()
+            .mode();
+        if mode & 0o077 != 0 {
+            return Err(fail(format!(
+                "permissions {:o} are too open; run `chmod 600 {}`",
+                mode & 0o777,
+                path.display()
+            )));
+        }
+    }
+    let text = std::fs::read_to_string(path).map_err(|err| fail(err.to_string()))?;
+    first_line(&text).ok_or_else(|| fail("file is empty".to_string()))
+}
+
+fn first_line(text: &str) -> Option<String> {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch path for `name`.
+    fn scratch(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("netero-key-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn key_file_must_be_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = scratch("file");
+        std::fs::write(&path, "\n  sk-file  \nsecond line\n").unwrap();
+        for mode in [0o640, 0o644, 0o604] {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            let err = read_key_file(&path).unwrap_err();
+            assert!(err.0.contains("too open"), "{:o}: {}", mode, err);
+        }
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(read_key_file(&path).unwrap(), "sk-file");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn key_command_runs_once() {
+        let runs = scratch("runs");
+        let key = ApiKey::Command(format!("echo run >> '{}'; echo sk-cmd", runs.display()));
+
+        assert_eq!(key.value().await.unwrap(), "sk-cmd");
+        assert_eq!(key.clone().value().await.unwrap(), "sk-cmd");
+
+        assert_eq!(std::fs::read_to_string(&runs).unwrap(), "run\n");
+        let _ = std::fs::remove_file(&runs);
+    }
+}
@@ -0,0 +1,288 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::Message;
+use crate::core::provider::{Backend, Params};
+
+/// How long cached answers stay valid unless `[cache] ttl_secs` says otherwise.
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How the on-disk response cache is used.
+#[derive(Clone, Copy, Debug)]
+pub struct CachePolicy {
+    /// Whether answers are looked up and stored at all.
+    pub enabled: bool,
+    /// Age after which an entry is ignored and may be pruned.
+    pub ttl: Duration,
+    /// Skips the lookup but still stores the fresh answer (`--refresh`).
+    pub refresh: bool,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl: DEFAULT_TTL,
+            refresh: false,
+        }
+    }
+}
+
+/// A cached answer, stored as `<key>.json`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Entry {
+    /// Unix time the answer was stored.
+    pub created: u64,
+    pub backend: String,
+    pub model: String,
+    pub content: String,
+}
+
+impl Entry {
+    /// Time elapsed since the entry was stored.
+    pub fn age(&self) -> Duration {
+        Duration::from_secs(now().saturating_sub(self.created))
+    }
+}
+
+/// Everything that determines an answer; its hash names the entry.
+#[derive(Serialize)]
+struct KeyInput<'a> {
+    backend: &'a str,
+    endpoint: &'a str,
+    model: &'a str,
+    params: &'a Params,
+    messages: &'a [Message],
+}
+
+/// Returns the cache key for a request.
+pub fn key(
+    backend: Backend,
+    endpoint: &str,
+    model: &str,
+    params: &Params,
+    messages: &[Message],
+) -> String {
+    let input = KeyInput {
+        backend: backend.as_str(),
+        endpoint,
+        model,
+        params,
+        messages,
+    };
+    let bytes = serde_json::to_vec(&input).unwrap_or_default();
+    format!("{:032x}", fnv1a(&bytes))
+}
+
+/// Returns the cached answer for `key` when one exists and is younger than `ttl`.
+pub fn load(key: &str, ttl: Duration) -> Option<Entry> {
+    let entry = read(&cache_dir()?.join(format!("{}.json", key))).ok()?;
+    (entry.age() <= ttl).then_some(entry)
+}
+
+/// Stores an answer under `key`, replacing any previous one.
+pub fn store(key: &str, backend: Backend, model: &str, content: &str) -> io::Result<()> {
+    let Some(dir) = cache_dir() else {
+        return Err(io::Error::other("no cache directory (HOME is not set)"));
+    };
+    store_in(&dir, key, backend, model, content)
+}
+
+/// Stores an answer in `dir`. Answers may quote private code, so the directory and the
+/// entries are readable by the owner alone.
+fn store_in(dir: &Path, key: &str, backend: Backend, model: &str, content: &str) -> io::Result<()> {
+    create_private_dir(dir)?;
+    let entry = Entry {
+        created: now(),
+        backend: backend.as_str().to_string(),
+        model: model.to_string(),
+        content: content.to_string(),
+    };
+    let data = serde_json::to_vec(&entry).map_err(io::Error::other)?;
+    // Write then rename, so a concurrent reader never sees half an entry.
+    let tmp = dir.join(format!("{}.json.tmp", key));
+    write_private(&tmp, &data)?;
+    fs::rename(&tmp, dir.join(format!("{}.json", key)))
+}
+
+/// Creates `dir` with mode 0700, tightening it when it already exists.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+    }
+    #[cfg(not(unix))]
+    fs::create_dir_all(dir)
+}
+
+/// Writes `path` with mode 0600, tightening a leftover file that already exists.
+fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        io::Write::write_all(&mut file, data)
+    }
+    #[cfg(not(unix))]
+    io::Write::write_all(&mut options.open(path)?, data)
+}
+
+/// Lists the stored entries with their keys, oldest first; unreadable files are skipped.
+pub fn entries() -> io::Result<Vec<(String, Entry)>> {
+    let Some(dir) = cache_dir() else {
+        return Ok(Vec::new());
+    };
+    let listing = match fs::read_dir(&dir) {
+        Ok(listing) => listing,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut entries = Vec::new();
+    for item in listing {
+        let path = item?.path();
+        let Some(key) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".json"))
+        else {
+            continue;
+        };
+        if let Ok(entry) = read(&path) {
+            entries.push((key.to_string(), entry));
+        }
+    }
+    entries.sort_by_key(|(_, entry)| entry.created);
+    Ok(entries)
+}
+
+/// Removes the entries older than `ttl`, or every entry when `ttl` is `None`.
+/// Returns how many were removed.
+pub fn prune(ttl: Option<Duration>) -> io::Result<usize> {
+    let Some(dir) = cache_dir() else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for (key, entry) in entries()? {
+        if ttl.is_none_or(|ttl| entry.age() > ttl) {
+            fs::remove_file(dir.join(format!("{}.json", key)))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Returns the cache directory: `$XDG_CACHE_HOME/netero`, then `~/.cache/netero`.
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Ok(value) = std::env::var("XDG_CACHE_HOME") {
+        let trimmed = value.trim();
+        if !trimmed.is_empty() {
+            return Some(Path::new(trimmed).join("netero"));
+        }
+    }
+
+    let home = std::env::var("HOME").ok()?;
+    Some(Path::new(&home).join(".cache").join("netero"))
+}
+
+fn read(path: &Path) -> io::Result<Entry> {
+    let data = fs::read(path)?;
+    serde_json::from_slice(&data).map_err(io::Error::other)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or(0)
+}
+
+/// 128-bit FNV-1a; stable across builds and platforms, unlike `std`'s hasher.
+fn fnv1a(bytes: &[u8]) -> u128 {
+    const OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    bytes.iter().fold(OFFSET, |hash, byte| {
+        (hash ^ u128::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_key(model: &str, params: &Params, prompt: &str) -> String {
+        key(
+            Backend::OpenAi,
+            "https://example.test/v1/chat/completions",
+            model,
+            params,
+            &[Message::user(prompt)],
+        )
+    }
+
+    #[test]
+    fn same_request_same_key() {
+        let params = Params::default();
+        assert_eq!(
+            request_key("m", &params, "hi"),
+            request_key("m", &params, "hi")
+        );
+    }
+
+    #[test]
+    fn key_changes_with_what_determines_the_answer() {
+        let params = Params::default();
+        let base = request_key("m", &params, "hi");
+        let warmer = Params {
+            temperature: Some(0.9),
+            ..Params::default()
+        };
+
+        assert_ne!(base, request_key("other", &params, "hi"));
+        assert_ne!(base, request_key("m", &warmer, "hi"));
+        assert_ne!(base, request_key("m", &params, "hello"));
+        assert_ne!(
+            base,
+            key(
+                Backend::Ollama,
+                "https://example.test/v1/chat/completions",
+                "m",
+                &params,
+                &[Message::user("hi")]
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn entries_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("netero-cache-test-{}", std::process::id()));
+        let cache = dir.join("netero");
+        fs::create_dir_all(&cache).unwrap();
+        fs::set_permissions(&cache, fs::Permissions::from_mode(0o755)).unwrap();
+
+        store_in(&cache, "k", Backend::OpenAi, "m", "answer").unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&cache), 0o700);
+        assert_eq!(mode(&cache.join("k.json")), 0o600);
+        assert_eq!(read(&cache.join("k.json")).unwrap().content, "answer");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,46 +1,974 @@
+mod file;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 use crate::core::Cli;
+use crate::core::apikey::ApiKey;
+use crate::core::cache::CachePolicy;
+use crate::core::http::HttpSettings;
+use crate::core::provider::{Backend, Cassette, Params};
+use crate::core::retry::RetryPolicy;
+
+pub use file::{HttpSection, Layer, PROJECT_FILE, Profile, config_path};
+
+/// Model used by the built-in codestral provider.
+const DEFAULT_MODEL: &str = "codestral-latest";
+
+/// Tasks that can be routed to their own profile through `[tasks]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Task {
+    Commit,
+    Prompt,
+    Chat,
+    Trans,
+    Save,
+}
+
+impl Task {
+    pub const ALL: [Task; 5] = [
+        Task::Commit,
+        Task::Prompt,
+        Task::Chat,
+        Task::Trans,
+        Task::Save,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Task::Commit => "commit",
+            Task::Prompt => "prompt",
+            Task::Chat => "chat",
+            Task::Trans => "trans",
+            Task::Save => "save",
+        }
+    }
+}
+
+/// Where an effective setting came from.
+#[derive(Clone, Debug)]
+pub enum Source {
+    /// Built-in default.
+    Default,
+    /// Top-level key of a config file.
+    File(PathBuf),
+    /// Key of a named profile in a config file.
+    Profile(String, PathBuf),
+    /// Environment variable.
+    Env(String),
+    /// Command line flag.
+    Flag(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Profile(name, path) => write!(f, "profile {} ({})", name, path.display()),
+            Source::Env(name) => write!(f, "env {}", name),
+            Source::Flag(name) => write!(f, "flag {}", name),
+        }
+    }
+}
 
-/// Configuration resolved from environment variables and CLI flags.
+/// Configuration resolved from the config files, environment variables and CLI flags.
+#[derive(Clone)]
 pub struct Config {
+    /// Name of the selected profile, if any.
+    pub profile: Option<String>,
+    pub backend: Backend,
     pub endpoint: String,
     pub model: String,
-    pub apikey: Option<String>,
+    pub apikey: Option<ApiKey>,
+    pub params: Params,
+    /// Commit convention file, already resolved against its config file.
+    pub convention: Option<PathBuf>,
+    /// Glob patterns excluded from automatic attachment.
+    pub attach_ignore: Vec<String>,
+    /// Chat persona replacing the default assistant role.
+    pub persona: Option<String>,
+    /// Profile names mapped to tasks in `[tasks]`.
+    pub tasks: Vec<(Task, String)>,
+    pub retry: RetryPolicy,
+    pub cache: CachePolicy,
+    /// Whether prompt and commit replies are printed as they arrive.
+    pub stream: bool,
+    pub http: HttpSettings,
+    /// Cassette recording or replaying the provider traffic.
+    pub cassette: Option<Cassette>,
+    /// Profiles tried in order when the selected provider is unreachable.
+    pub fallback: Vec<String>,
+    /// Delay after which the first fallback is raced against the selected provider.
+    pub hedge_after: Option<Duration>,
     pub verbose: bool,
+    /// Global config file, if one was found.
+    pub global: Option<Layer>,
+    /// Project `.netero.toml`, if one was found.
+    pub project: Option<Layer>,
+    /// Origin of every effective value, in display order.
+    pub origins: Vec<(String, Source)>,
 }
 
 impl Config {
-    /// Loads configuration from environment variables with sensible defaults.
-    pub fn from_env(args: &Cli) -> Self {
-        // Read env vars only once to keep behavior consistent.
-        let url = std::env::var("NETERO_URL")
-            .ok()
-            .filter(|v| !v.trim().is_empty());
-
-        let model = std::env::var("NETERO_MODEL")
-            .ok()
-            .filter(|v| !v.trim().is_empty());
-
-        let key = std::env::var("NETERO_API_KEY")
-            .ok()
-            .filter(|v| !v.trim().is_empty());
-
-        let (endpoint, model, apikey) = match (url, model) {
-            (Some(u), Some(m)) => (u, m, key),
-            (None, None) => (
-                "https://codestral.mistral.ai/v1/chat/completions".to_string(),
-                "codestral-latest".to_string(),
-                std::env::var("CODE_API_KEY")
-                    .ok()
-                    .filter(|v| !v.trim().is_empty()),
+    /// Loads the global and project config files and applies env var and flag overrides.
+    pub fn load(args: &Cli) -> Result<Self, String> {
+        let global = Layer::global()?;
+        let project = Layer::project()?;
+        Self::resolve(args, global, project, None, &process_env)
+    }
+
+    /// Resolves the configuration for a task, using its `[tasks]` profile when one is mapped.
+    pub fn for_task(&self, args: &Cli, task: Task) -> Result<Self, String> {
+        Self::resolve(
+            args,
+            self.global.clone(),
+            self.project.clone(),
+            Some(task),
+            &process_env,
+        )
+    }
+
+    /// Resolves the configuration for a task with `profile` selected, as `--profile` would.
+    /// A `--model` flag is dropped so the profile's own model applies.
+    pub fn for_profile(&self, args: &Cli, task: Task, profile: &str) -> Result<Self, String> {
+        let mut args = args.clone();
+        args.profile = Some(profile.to_string());
+        args.model = None;
+        Self::resolve(
+            &args,
+            self.global.clone(),
+            self.project.clone(),
+            Some(task),
+            &process_env,
+        )
+    }
+
+    /// Resolves fallback profile `name` for this configuration. Only the profile chooses the
+    /// backend, endpoint, model and key, since the env vars and flags that override them
+    /// describe the primary provider; generation parameters and retries are kept.
+    pub fn fallback(&self, args: &Cli, name: &str) -> Result<Self, String> {
+        let Some((profile, path)) = self.global.as_ref().and_then(|layer| {
+            layer
+                .file
+                .profiles
+                .get(name)
+                .map(|profile| (profile, &layer.path))
+        }) else {
+            return Err(format!("unknown fallback profile: {}", name));
+        };
+        let source = Source::Profile(name.to_string(), path.clone());
+        let set = |value: &Option<String>| value.clone().filter(|v| !v.trim().is_empty());
+
+        let backend = match set(&profile.backend) {
+            Some(value) => value
+                .parse()
+                .map_err(|err| format!("{}: {}", source, err))?,
+            None => Backend::OpenAi,
+        };
+        let Some(model) = set(&profile.model) else {
+            return Err(format!("fallback profile {} has no `model`", name));
+        };
+        let endpoint = set(&profile.endpoint);
+        let endpoint_source = match endpoint {
+            Some(_) => source.clone(),
+            None => Source::Default,
+        };
+        let endpoint = endpoint.unwrap_or_else(|| backend.default_endpoint().to_string());
+        let vendor_key = backend != Backend::OpenAi || endpoint == backend.default_endpoint();
+        let apikey = profile_key(profile, path, &source, &process_env).or_else(|| {
+            vendor_key
+                .then(|| env_source(&process_env, backend.key_var()))
+                .flatten()
+                .map(|(value, source)| (ApiKey::Value(value), source))
+        });
+
+        let mut config = self.clone();
+        config.origins.retain(|(key, _)| {
+            !matches!(
+                key.as_str(),
+                "profile" | "backend" | "endpoint" | "model" | "api_key"
+            ) && !key.starts_with("http.")
+        });
+        config.http = http_settings(
+            self.global.as_ref(),
+            Some((name, profile)),
+            args.insecure,
+            &mut config.origins,
+        );
+        config.origins.push(("profile".to_string(), source.clone()));
+        config.origins.push(("backend".to_string(), source.clone()));
+        config
+            .origins
+            .push(("endpoint".to_string(), endpoint_source));
+        config.origins.push(("model".to_string(), source));
+        config.profile = Some(name.to_string());
+        config.backend = backend;
+        config.endpoint = endpoint;
+        config.model = model;
+        config.apikey = apikey.map(|(value, source)| {
+            config.origins.push(("api_key".to_string(), source));
+            value
+        });
+        // A fallback never chains further.
+        config.fallback = Vec::new();
+        config.hedge_after = None;
+        Ok(config)
+    }
+
+    /// Names of the profiles defined in the global config file.
+    pub fn profile_names(&self) -> Vec<String> {
+        self.global
+            .as_ref()
+            .map(|layer| layer.file.profiles.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Models named by the profiles of the global config file.
+    pub fn profile_models(&self) -> Vec<String> {
+        self.global
+            .as_ref()
+            .map(|layer| {
+                layer
+                    .file
+                    .profiles
+                    .values()
+                    .filter_map(|profile| profile.model.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns where the given setting came from.
+    pub fn origin(&self, key: &str) -> Option<&Source> {
+        self.origins
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, source)| source)
+    }
+
+    /// Merges the layers; precedence is flags, env vars, project file, then global file.
+    /// Env vars are read through `env`.
+    fn resolve(
+        args: &Cli,
+        global: Option<Layer>,
+        project: Option<Layer>,
+        task: Option<Task>,
+        env: &EnvLookup<'_>,
+    ) -> Result<Self, String> {
+        let mut origins = Vec::new();
+        let layers = [project.as_ref(), global.as_ref()];
+
+        // Task routes: the nearest file that maps a task wins.
+        let mut routes = Vec::new();
+        for each in Task::ALL {
+            if let Some(layer) = layers
+                .into_iter()
+                .flatten()
+                .find(|layer| layer.file.tasks.get(each).is_some())
+            {
+                let value = layer.file.tasks.get(each).cloned().unwrap_or_default();
+                routes.push((each, value, Source::File(layer.path.clone())));
+            }
+        }
+
+        // Profile name: --profile / NETERO_PROFILE, then the task route, then the files.
+        let mut name = args.profile.clone().map(|value| {
+            let source = if read_var(env, "NETERO_PROFILE").as_deref() == Some(value.as_str()) {
+                Source::Env("NETERO_PROFILE".to_string())
+            } else {
+                Source::Flag("--profile".to_string())
+            };
+            (value, source)
+        });
+        if name.is_none() {
+            name = routes
+                .iter()
+                .find(|(each, _, _)| Some(*each) == task)
+                .map(|(_, value, source)| (value.clone(), source.clone()));
+        }
+        for layer in layers.into_iter().flatten() {
+            if name.is_none()
+                && let Some(value) = &layer.file.profile
+            {
+                name = Some((value.clone(), Source::File(layer.path.clone())));
+            }
+        }
+
+        // Profiles live only in the global file.
+        let profile = match &name {
+            Some((value, source)) => {
+                origins.push(("profile".to_string(), source.clone()));
+                let found = global
+                    .as_ref()
+                    .and_then(|layer| layer.file.profiles.get(value).map(|p| (p, &layer.path)));
+                match found {
+                    Some((profile, path)) => Some((value.clone(), profile.clone(), path.clone())),
+                    None => return Err(format!("unknown profile: {}", value)),
+                }
+            }
+            None => None,
+        };
+        let profile_source =
+            |name: &str, path: &Path| Source::Profile(name.to_string(), path.to_path_buf());
+        let from_profile = |field: fn(&Profile) -> Option<String>| {
+            profile.as_ref().and_then(|(name, p, path)| {
+                field(p)
+                    .filter(|v| !v.trim().is_empty())
+                    .map(|v| (v, profile_source(name, path)))
+            })
+        };
+
+        // Env vars override the profile; `--model` overrides both.
+        let url = env_source(env, "NETERO_URL").or_else(|| from_profile(|p| p.endpoint.clone()));
+        let model = args
+            .model
+            .clone()
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| (value, Source::Flag("--model".to_string())))
+            .or_else(|| env_source(env, "NETERO_MODEL"))
+            .or_else(|| from_profile(|p| p.model.clone()));
+        let backend = match env_source(env, "NETERO_BACKEND")
+            .or_else(|| from_profile(|p| p.backend.clone()))
+        {
+            Some((value, source)) => {
+                let backend = value
+                    .parse()
+                    .map_err(|err| format!("{}: {}", source, err))?;
+                origins.push(("backend".to_string(), source));
+                backend
+            }
+            None => {
+                origins.push(("backend".to_string(), Source::Default));
+                Backend::OpenAi
+            }
+        };
+
+        let (endpoint, model) = match (backend, url, model) {
+            (_, Some(u), Some(m)) => (u, m),
+            (Backend::OpenAi, None, None) => (
+                (backend.default_endpoint().to_string(), Source::Default),
+                (DEFAULT_MODEL.to_string(), Source::Default),
             ),
-            _ => panic!("NETERO_URL and NETERO_MODEL must be set together"),
+            (Backend::OpenAi, Some(_), None) => {
+                return Err("an endpoint is configured without a model; \
+                    set NETERO_MODEL or `model` in the profile"
+                    .to_string());
+            }
+            // `--model` alone picks another model on the default endpoint.
+            (Backend::OpenAi, None, Some(m)) if matches!(m.1, Source::Flag(_)) => {
+                ((backend.default_endpoint().to_string(), Source::Default), m)
+            }
+            (Backend::OpenAi, None, Some(_)) => {
+                return Err("a model is configured without an endpoint; \
+                    set NETERO_URL or `endpoint` in the profile"
+                    .to_string());
+            }
+            // Other backends have a known public endpoint but no default model.
+            (_, None, Some(m)) => ((backend.default_endpoint().to_string(), Source::Default), m),
+            (_, _, None) => {
+                return Err(format!(
+                    "no model configured for the {} backend; \
+                    set NETERO_MODEL or `model` in the profile",
+                    backend.as_str()
+                ));
+            }
         };
+        origins.push(("endpoint".to_string(), endpoint.1));
+        origins.push(("model".to_string(), model.1));
+        let (endpoint, model) = (endpoint.0, model.0);
+
+        // CODE_API_KEY belongs to codestral, so it never leaks to custom OpenAI endpoints.
+        let vendor_key = backend != Backend::OpenAi || endpoint == backend.default_endpoint();
+        let apikey = env_source(env, "NETERO_API_KEY")
+            .map(|(value, source)| (ApiKey::Value(value), source))
+            .or_else(|| {
+                env_source(env, "NETERO_API_KEY_CMD")
+                    .map(|(value, source)| (ApiKey::Command(value), source))
+            })
+            .or_else(|| {
+                env_source(env, "NETERO_API_KEY_FILE")
+                    .map(|(value, source)| (ApiKey::File(PathBuf::from(value)), source))
+            })
+            .or_else(|| {
+                let (name, profile, path) = profile.as_ref()?;
+                profile_key(profile, path, &profile_source(name, path), env)
+            })
+            .or_else(|| {
+                if vendor_key {
+                    env_source(env, backend.key_var())
+                        .map(|(value, source)| (ApiKey::Value(value), source))
+                } else {
+                    None
+                }
+            });
+        let apikey = apikey.map(|(value, source)| {
+            origins.push(("api_key".to_string(), source));
+            value
+        });
 
-        Self {
+        // Params: global file, profile, project file, env vars, then flags.
+        let mut params = Params::default();
+        let mut apply = |layer: &Params, source: Source| {
+            for key in layer.keys() {
+                let name = format!("params.{}", key);
+                origins.retain(|(each, _)| each != &name);
+                origins.push((name, source.clone()));
+            }
+            params.merge(layer);
+        };
+        if let Some(layer) = &global {
+            apply(&layer.file.params, Source::File(layer.path.clone()));
+        }
+        if let Some((name, profile, path)) = &profile {
+            apply(&profile.params, profile_source(name, path));
+        }
+        if let Some(layer) = &project {
+            apply(&layer.file.params, Source::File(layer.path.clone()));
+        }
+        for key in Params::KEYS {
+            let var = format!("NETERO_{}", key.to_uppercase());
+            if let Some(value) = read_var(env, &var) {
+                let mut single = Params::default();
+                single
+                    .set(key, value.trim())
+                    .map_err(|err| format!("{}: {}", var, err))?;
+                apply(&single, Source::Env(var));
+            }
+        }
+        let flags = args.params();
+        params.merge(&flags);
+        for key in flags.keys() {
+            let name = format!("params.{}", key);
+            origins.retain(|(each, _)| each != &name);
+            origins.push((name, Source::Flag(format!("--{}", key.replace('_', "-")))));
+        }
+
+        // Per-repository settings: the nearest file that sets a key wins.
+        let convention = match env_source(env, "NETERO_CONVENTION") {
+            Some((value, source)) => {
+                origins.push(("commit.convention".to_string(), source));
+                Some(PathBuf::from(value))
+            }
+            None => layers.into_iter().flatten().find_map(|layer| {
+                let path = layer.file.commit.convention.as_ref()?;
+                origins.push((
+                    "commit.convention".to_string(),
+                    Source::File(layer.path.clone()),
+                ));
+                Some(layer.relative(path))
+            }),
+        };
+        let attach_ignore = layers
+            .into_iter()
+            .flatten()
+            .find_map(|layer| {
+                let ignore = layer.file.attach.ignore.clone()?;
+                origins.push((
+                    "attach.ignore".to_string(),
+                    Source::File(layer.path.clone()),
+                ));
+                Some(ignore)
+            })
+            .unwrap_or_default();
+        let persona = layers.into_iter().flatten().find_map(|layer| {
+            let persona = layer.file.chat.persona.clone()?;
+            origins.push(("chat.persona".to_string(), Source::File(layer.path.clone())));
+            Some(persona)
+        });
+
+        let mut retry = RetryPolicy::default();
+        match env_source(env, "NETERO_RETRIES") {
+            Some((value, source)) => {
+                retry.max_retries = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| "NETERO_RETRIES must be a non-negative integer".to_string())?;
+                origins.push(("retry.max_retries".to_string(), source));
+            }
+            None => {
+                if let Some((value, source)) = nearest(&layers, |file| file.retry.max_retries) {
+                    retry.max_retries = value;
+                    origins.push(("retry.max_retries".to_string(), source));
+                }
+            }
+        }
+        if let Some((value, source)) = nearest(&layers, |file| file.retry.base_delay_ms) {
+            retry.base_delay = Duration::from_millis(value);
+            origins.push(("retry.base_delay_ms".to_string(), source));
+        }
+        if let Some((value, source)) = nearest(&layers, |file| file.retry.max_delay_ms) {
+            retry.max_delay = Duration::from_millis(value);
+            origins.push(("retry.max_delay_ms".to_string(), source));
+        }
+
+        let http = http_settings(
+            global.as_ref(),
+            profile
+                .as_ref()
+                .map(|(name, profile, _)| (name.as_str(), profile)),
+            args.insecure,
+            &mut origins,
+        );
+
+        let mut cache = CachePolicy::default();
+        match env_source(env, "NETERO_CACHE") {
+            Some((value, source)) => {
+                cache.enabled = match value.trim().to_ascii_lowercase().as_str() {
+                    "1" | "true" | "on" | "yes" => true,
+                    "0" | "false" | "off" | "no" => false,
+                    _ => return Err("NETERO_CACHE must be 1 or 0".to_string()),
+                };
+                origins.push(("cache.enabled".to_string(), source));
+            }
+            None => {
+                if let Some((value, source)) = nearest(&layers, |file| file.cache.enabled) {
+                    cache.enabled = value;
+                    origins.push(("cache.enabled".to_string(), source));
+                }
+            }
+        }
+        if let Some((value, source)) = nearest(&layers, |file| file.cache.ttl_secs) {
+            cache.ttl = Duration::from_secs(value);
+            origins.push(("cache.ttl_secs".to_string(), source));
+        }
+        // `--refresh` stores a fresh answer even when the cache is off; `--no-cache` wins.
+        if args.refresh {
+            cache.enabled = true;
+            cache.refresh = true;
+            origins.retain(|(key, _)| key != "cache.enabled");
+            origins.push((
+                "cache.enabled".to_string(),
+                Source::Flag("--refresh".to_string()),
+            ));
+        }
+        if args.no_cache {
+            cache.enabled = false;
+            origins.retain(|(key, _)| key != "cache.enabled");
+            origins.push((
+                "cache.enabled".to_string(),
+                Source::Flag("--no-cache".to_string()),
+            ));
+        }
+
+        let mut stream = false;
+        match env_source(env, "NETERO_STREAM") {
+            Some((value, source)) => {
+                stream = match value.trim().to_ascii_lowercase().as_str() {
+                    "1" | "true" | "on" | "yes" => true,
+                    "0" | "false" | "off" | "no" => false,
+                    _ => return Err("NETERO_STREAM must be 1 or 0".to_string()),
+                };
+                origins.push(("output.stream".to_string(), source));
+            }
+            None => {
+                if let Some((value, source)) = nearest(&layers, |file| file.output.stream) {
+                    stream = value;
+                    origins.push(("output.stream".to_string(), source));
+                }
+            }
+        }
+        if args.stream || args.no_stream {
+            stream = args.stream;
+            let flag = if args.stream {
+                "--stream"
+            } else {
+                "--no-stream"
+            };
+            origins.retain(|(key, _)| key != "output.stream");
+            origins.push(("output.stream".to_string(), Source::Flag(flag.to_string())));
+        }
+
+        let mut fallback = Vec::new();
+        let mut hedge_after = None;
+        if let Some((name, profile, path)) = &profile {
+            if !profile.fallback.is_empty() {
+                fallback = profile.fallback.clone();
+                origins.push(("fallback".to_string(), profile_source(name, path)));
+            }
+            if let Some(ms) = profile.hedge_after_ms {
+                hedge_after = Some(Duration::from_millis(ms));
+                origins.push(("hedge_after_ms".to_string(), profile_source(name, path)));
+            }
+        }
+
+        Ok(Self {
+            profile: name.map(|(value, _)| value),
+            backend,
             endpoint,
             model,
             apikey,
+            params,
+            convention,
+            attach_ignore,
+            persona,
+            tasks: routes
+                .into_iter()
+                .map(|(each, value, source)| {
+                    origins.push((format!("tasks.{}", each.as_str()), source));
+                    (each, value)
+                })
+                .collect(),
+            retry,
+            cache,
+            stream,
+            http,
+            cassette: match (&args.replay, &args.record) {
+                (Some(path), _) => Some(Cassette::Replay(path.clone())),
+                (None, Some(path)) => Some(Cassette::Record(path.clone())),
+                (None, None) => None,
+            },
+            fallback,
+            hedge_after,
             verbose: args.verbose,
+            global,
+            project,
+            origins,
+        })
+    }
+}
+
+/// Merges the global `[http]` with the profile's own `http` table, field by field;
+/// `--insecure` wins over both.
+fn http_settings(
+    global: Option<&Layer>,
+    profile: Option<(&str, &Profile)>,
+    insecure: bool,
+    origins: &mut Vec<(String, Source)>,
+) -> HttpSettings {
+    let mut settings = HttpSettings::default();
+    if let Some(layer) = global {
+        let mut pick = |key: &str, get: &dyn Fn(&HttpSection) -> bool| {
+            let section = match profile {
+                Some((name, p)) if get(&p.http) => {
+                    let source = Source::Profile(name.to_string(), layer.path.clone());
+                    origins.push((format!("http.{}", key), source));
+                    &p.http
+                }
+                _ if get(&layer.file.http) => {
+                    origins.push((format!("http.{}", key), Source::File(layer.path.clone())));
+                    &layer.file.http
+                }
+                _ => return None,
+            };
+            Some(section)
+        };
+        let path = |value: &PathBuf| layer.relative(value);
+
+        settings.proxy = pick("proxy", &|s| s.proxy.is_some()).and_then(|s| s.proxy.clone());
+        settings.no_proxy =
+            pick("no_proxy", &|s| s.no_proxy.is_some()).and_then(|s| s.no_proxy.clone());
+        settings.ca_bundle = pick("ca_bundle", &|s| s.ca_bundle.is_some())
+            .and_then(|s| s.ca_bundle.as_ref().map(path));
+        settings.client_cert = pick("client_cert", &|s| s.client_cert.is_some())
+            .and_then(|s| s.client_cert.as_ref().map(path));
+        settings.client_key = pick("client_key", &|s| s.client_key.is_some())
+            .and_then(|s| s.client_key.as_ref().map(path));
+        settings.connect_timeout = pick("connect_timeout_ms", &|s| s.connect_timeout_ms.is_some())
+            .and_then(|s| s.connect_timeout_ms.map(Duration::from_millis));
+        settings.read_timeout = pick("read_timeout_ms", &|s| s.read_timeout_ms.is_some())
+            .and_then(|s| s.read_timeout_ms.map(Duration::from_millis));
+        settings.insecure = pick("insecure", &|s| s.insecure.is_some())
+            .and_then(|s| s.insecure)
+            .unwrap_or(false);
+    }
+    if insecure {
+        settings.insecure = true;
+        origins.retain(|(key, _)| key != "http.insecure");
+        origins.push((
+            "http.insecure".to_string(),
+            Source::Flag("--insecure".to_string()),
+        ));
+    }
+    settings
+}
+
+/// Returns the key a profile names: `api_key`, then `key_command`, `api_key_file` and
+/// `api_key_env`. `path` is the config file defining the profile.
+fn profile_key(
+    profile: &Profile,
+    path: &Path,
+    source: &Source,
+    env: &EnvLookup<'_>,
+) -> Option<(ApiKey, Source)> {
+    let set = |value: &Option<String>| value.clone().filter(|v| !v.trim().is_empty());
+    if let Some(value) = set(&profile.api_key) {
+        return Some((ApiKey::Value(value), source.clone()));
+    }
+    if let Some(command) = set(&profile.key_command) {
+        return Some((ApiKey::Command(command), source.clone()));
+    }
+    if let Some(file) = &profile.api_key_file {
+        let file = match path.parent() {
+            Some(dir) => dir.join(file),
+            None => file.clone(),
+        };
+        return Some((ApiKey::File(file), source.clone()));
+    }
+    env_source(env, profile.api_key_env.as_deref()?)
+        .map(|(value, source)| (ApiKey::Value(value), source))
+}
+
+/// Returns the value set by the nearest file, together with its source.
+fn nearest<T>(
+    layers: &[Option<&Layer>],
+    field: impl Fn(&file::FileConfig) -> Option<T>,
+) -> Option<(T, Source)> {
+    layers
+        .iter()
+        .flatten()
+        .find_map(|layer| Some((field(&layer.file)?, Source::File(layer.path.clone()))))
+}
+
+/// Looks up an env var by name.
+type EnvLookup<'a> = dyn Fn(&str) -> Option<String> + 'a;
+
+/// The real environment, used outside tests.
+fn process_env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Reads an env var together with its source.
+fn env_source(env: &EnvLookup<'_>, name: &str) -> Option<(String, Source)> {
+    read_var(env, name).map(|value| (value, Source::Env(name.to_string())))
+}
+
+/// Reads an env var, treating blank values as unset.
+fn read_var(env: &EnvLookup<'_>, name: &str) -> Option<String> {
+    env(name).filter(|v| !v.trim().is_empty())
+}
+
+/// Parses `args` as flags, ignoring the env vars clap would read for them.
+#[cfg(test)]
+pub fn cli(args: &[&str]) -> Cli {
+    use clap::{CommandFactory, FromArgMatches};
+
+    let matches = Cli::command()
+        .mut_args(|arg| arg.env(None))
+        .get_matches_from(std::iter::once("netero").chain(args.iter().copied()));
+    Cli::from_arg_matches(&matches).expect("valid flags")
+}
+
+/// Built-in defaults pointed at `endpoint` with fast retries, for tests against a mock
+/// server. The environment is not read.
+#[cfg(test)]
+pub fn for_endpoint(backend: Backend, endpoint: &str, model: &str) -> Config {
+    let mut config =
+        Config::resolve(&cli(&[]), None, None, None, &|_| None).expect("built-in defaults");
+    config.backend = backend;
+    config.endpoint = endpoint.to_string();
+    config.model = model.to_string();
+    config.apikey = Some(ApiKey::Value("sk-test".to_string()));
+    config.http.no_proxy = Some("*".to_string());
+    config.retry.base_delay = Duration::from_millis(1);
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// No env vars set.
+    fn clean(_: &str) -> Option<String> {
+        None
+    }
+
+    fn layer(path: &str, toml: &str) -> Layer {
+        Layer {
+            path: PathBuf::from(path),
+            file: toml::from_str(toml).expect("valid config"),
         }
     }
+
+    const GLOBAL: &str = r#"
+        profile = "home"
+
+        [profiles.home]
+        backend = "ollama"
+        model = "llama3"
+
+        [profiles.work]
+        backend = "anthropic"
+        model = "claude-test"
+        api_key = "sk-work"
+        params = { temperature = 0.2 }
+    "#;
+
+    #[test]
+    fn default_profile_comes_from_the_file() {
+        let global = layer("/etc/netero.toml", GLOBAL);
+        let config = Config::resolve(&cli(&[]), Some(global), None, None, &clean).unwrap();
+        assert_eq!(config.profile.as_deref(), Some("home"));
+        assert_eq!(config.backend, Backend::Ollama);
+        assert_eq!(config.endpoint, Backend::Ollama.default_endpoint());
+        assert_eq!(config.model, "llama3");
+        assert!(matches!(config.origin("model"), Some(Source::Profile(name, _)) if name == "home"));
+    }
+
+    #[test]
+    fn profile_flag_overrides_the_file() {
+        let global = layer("/etc/netero.toml", GLOBAL);
+        let config = Config::resolve(
+            &cli(&["--profile", "work"]),
+            Some(global),
+            None,
+            None,
+            &clean,
+        )
+        .unwrap();
+        assert_eq!(config.backend, Backend::Anthropic);
+        assert_eq!(config.model, "claude-test");
+        assert!(matches!(&config.apikey, Some(ApiKey::Value(key)) if key == "sk-work"));
+        assert_eq!(config.params.temperature, Some(0.2));
+    }
+
+    #[test]
+    fn model_flag_overrides_the_profile() {
+        let global = layer("/etc/netero.toml", GLOBAL);
+        let config = Config::resolve(
+            &cli(&["--model", "llama3.2"]),
+            Some(global),
+            None,
+            None,
+            &clean,
+        )
+        .unwrap();
+        assert_eq!(config.model, "llama3.2");
+        assert!(matches!(config.origin("model"), Some(Source::Flag(_))));
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        let global = layer("/etc/netero.toml", GLOBAL);
+        let err = Config::resolve(
+            &cli(&["--profile", "nope"]),
+            Some(global),
+            None,
+            None,
+            &clean,
+        )
+        .err()
+        .expect("the profile is rejected");
+        assert_eq!(err, "unknown profile: nope");
+    }
+
+    #[test]
+    fn no_config_uses_the_built_in_provider() {
+        let config = Config::resolve(&cli(&[]), None, None, None, &clean).unwrap();
+        assert_eq!(config.profile, None);
+        assert_eq!(config.backend, Backend::OpenAi);
+        assert_eq!(config.model, DEFAULT_MODEL);
+        assert!(matches!(config.origin("model"), Some(Source::Default)));
+    }
+
+    #[test]
+    fn project_file_overrides_the_global_file() {
+        let global = layer(
+            "/home/me/.config/netero/config.toml",
+            r#"
+                [profiles.home]
+                model = "m"
+                endpoint = "http://localhost"
+                params = { temperature = 0.2, top_p = 0.9 }
+
+                [attach]
+                ignore = ["*.key"]
+
+                [params]
+                seed = 1
+                max_tokens = 100
+            "#,
+        );
+        let project = layer(
+            "/src/app/.netero.toml",
+            r#"
+                profile = "home"
+
+                [commit]
+                convention = "docs/commits.md"
+
+                [attach]
+                ignore = ["target/*"]
+
+                [params]
+                temperature = 0.7
+                max_tokens = 200
+            "#,
+        );
+        let config = Config::resolve(
+            &cli(&["--top-p", "0.5"]),
+            Some(global),
+            Some(project),
+            None,
+            &clean,
+        )
+        .unwrap();
+
+        assert_eq!(config.profile.as_deref(), Some("home"));
+        // Global file < profile < project file < flags.
+        assert_eq!(config.params.seed, Some(1));
+        assert_eq!(config.params.temperature, Some(0.7));
+        assert_eq!(config.params.max_tokens, Some(200));
+        assert_eq!(config.params.top_p, Some(0.5));
+        assert!(matches!(
+            config.origin("params.top_p"),
+            Some(Source::Flag(_))
+        ));
+        // The nearest file wins whole settings, and its paths are relative to it.
+        assert_eq!(config.attach_ignore, ["target/*"]);
+        assert_eq!(
+            config.convention,
+            Some(PathBuf::from("/src/app/docs/commits.md"))
+        );
+    }
+
+    #[test]
+    fn unknown_backend_is_a_config_error() {
+        let global = layer(
+            "/etc/netero.toml",
+            r#"
+                [profiles.work]
+                backend = "bogus"
+                model = "m"
+                endpoint = "http://localhost"
+            "#,
+        );
+        let err = Config::resolve(
+            &cli(&["--profile", "work"]),
+            Some(global),
+            None,
+            None,
+            &clean,
+        )
+        .err()
+        .expect("the backend is rejected");
+        assert_eq!(
+            err,
+            "profile work (/etc/netero.toml): unknown backend: bogus"
+        );
+    }
+
+    #[test]
+    fn env_vars_override_the_profile() {
+        let global = layer("/etc/netero.toml", GLOBAL);
+        let vars = HashMap::from([
+            ("NETERO_MODEL", "from-env"),
+            ("NETERO_TEMPERATURE", "0.9"),
+            ("NETERO_URL", " "),
+        ]);
+        let env = |name: &str| vars.get(name).map(|value| value.to_string());
+
+        let config = Config::resolve(&cli(&[]), Some(global), None, None, &env).unwrap();
+
+        assert_eq!(config.model, "from-env");
+        assert!(
+            matches!(config.origin("model"), Some(Source::Env(name)) if name == "NETERO_MODEL")
+        );
+        assert_eq!(config.params.temperature, Some(0.9));
+        // Blank values count as unset.
+        assert_eq!(config.endpoint, Backend::Ollama.default_endpoint());
+    }
 }
@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::Task;
+use crate::core::provider::Params;
+
+/// Name of the per-repository config file.
+pub const PROJECT_FILE: &str = ".netero.toml";
+
+/// Contents of `config.toml` or `.netero.toml`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    /// Profile used when neither `--profile` nor `NETERO_PROFILE` is given.
+    pub profile: Option<String>,
+    /// Named provider profiles (global config only).
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    #[serde(default)]
+    pub commit: CommitSection,
+    #[serde(default)]
+    pub attach: AttachSection,
+    #[serde(default)]
+    pub chat: ChatSection,
+    #[serde(default)]
+    pub tasks: TasksSection,
+    #[serde(default)]
+    pub retry: RetrySection,
+    #[serde(default)]
+    pub cache: CacheSection,
+    #[serde(default)]
+    pub output: OutputSection,
+    /// Proxy, TLS and timeout settings (global config only).
+    #[serde(default)]
+    pub http: HttpSection,
+    /// Default generation parameters, below profile and env overrides.
+    #[serde(default)]
+    pub params: Params,
+}
+
+/// Named provider settings.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// Backend name (`openai`, `anthropic`, `ollama`, `gemini`).
+    pub backend: Option<String>,
+    pub endpoint: Option<String>,
+    pub model: Option<String>,
+    /// Literal API key. Prefer `api_key_env` to keep secrets out of the file.
+    pub api_key: Option<String>,
+    /// Name of the env var holding the API key.
+    pub api_key_env: Option<String>,
+    /// Shell command printing the API key, such as `pass show mistral`.
+    pub key_command: Option<String>,
+    /// File holding the API key, relative to the config file; must be private (0600).
+    pub api_key_file: Option<PathBuf>,
+    /// Default generation parameters for this profile.
+    #[serde(default)]
+    pub params: Params,
+    /// Profiles tried in order when this one fails with a connection error, timeout or 5xx.
+    #[serde(default)]
+    pub fallback: Vec<String>,
+    /// Races the first fallback once this profile has not answered within this many ms.
+    pub hedge_after_ms: Option<u64>,
+    /// Network settings for this profile's endpoint, over the global `[http]`.
+    #[serde(default)]
+    pub http: HttpSection,
+}
+
+/// `[commit]` settings.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommitSection {
+    /// Commit convention file, relative to the config file.
+    pub convention: Option<PathBuf>,
+}
+
+/// `[attach]` settings.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AttachSection {
+    /// Glob patterns for paths that are never attached automatically.
+    pub ignore: Option<Vec<String>>,
+}
+
+/// `[chat]` settings.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChatSection {
+    /// Role description placed at the top of the chat system prompt.
+    pub persona: Option<String>,
+}
+
+/// `[retry]` settings for transient backend failures.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetrySection {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: Option<u32>,
+    /// Delay before the first retry, in milliseconds.
+    pub base_delay_ms: Option<u64>,
+    /// Longest single wait, in milliseconds.
+    pub max_delay_ms: Option<u64>,
+}
+
+/// `[cache]` settings for the on-disk response cache.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheSection {
+    /// Reuse stored answers for identical requests.
+    pub enabled: Option<bool>,
+    /// How long an answer stays valid, in seconds.
+    pub ttl_secs: Option<u64>,
+}
+
+/// `[output]` settings.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputSection {
+    /// Print prompt and commit replies as they are generated.
+    pub stream: Option<bool>,
+}
+
+/// `[http]` settings; relative paths are resolved against the config file.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpSection {
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy URL.
+    pub proxy: Option<String>,
+    /// Hosts that bypass the proxy, comma separated.
+    pub no_proxy: Option<String>,
+    /// PEM bundle of extra root certificates.
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM client certificate, with its key unless `client_key` is set.
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    pub connect_timeout_ms: Option<u64>,
+    pub read_timeout_ms: Option<u64>,
+    /// Accept invalid TLS certificates.
+    pub insecure: Option<bool>,
+}
+
+/// `[tasks]` settings: the profile each task runs with.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TasksSection {
+    pub commit: Option<String>,
+    pub prompt: Option<String>,
+    pub chat: Option<String>,
+    pub trans: Option<String>,
+    pub save: Option<String>,
+}
+
+impl TasksSection {
+    /// Returns the profile mapped to `task`, if any.
+    pub fn get(&self, task: Task) -> Option<&String> {
+        match task {
+            Task::Commit => self.commit.as_ref(),
+            Task::Prompt => self.prompt.as_ref(),
+            Task::Chat => self.chat.as_ref(),
+            Task::Trans => self.trans.as_ref(),
+            Task::Save => self.save.as_ref(),
+        }
+    }
+}
+
+/// A config file together with the path it was read from.
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub path: PathBuf,
+    pub file: FileConfig,
+}
+
+impl Layer {
+    /// Reads the global config file, if it exists.
+    pub fn global() -> Result<Option<Self>, String> {
+        match config_path() {
+            Some(path) if path.is_file() => Self::read(path).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Finds `.netero.toml` in the current directory or its nearest ancestor.
+    pub fn project() -> Result<Option<Self>, String> {
+        let Ok(cwd) = std::env::current_dir() else {
+            return Ok(None);
+        };
+        let Some(path) = cwd
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+        else {
+            return Ok(None);
+        };
+
+        let layer = Self::read(path)?;
+        // A checked-out repository must not be able to point our keys at its own endpoint.
+        if !layer.file.profiles.is_empty() {
+            return Err(format!(
+                "{}: profiles can only be defined in the global config",
+                layer.path.display()
+            ));
+        }
+        // Nor route them through its own proxy, or turn off certificate checks.
+        if layer.file.http != HttpSection::default() {
+            return Err(format!(
+                "{}: [http] can only be set in the global config",
+                layer.path.display()
+            ));
+        }
+        Ok(Some(layer))
+    }
+
+    fn read(path: PathBuf) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let file = toml::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Ok(Self { path, file })
+    }
+
+    /// Resolves a path written in this file relative to the file's directory.
+    pub fn relative(&self, path: &Path) -> PathBuf {
+        match self.path.parent() {
+            Some(dir) => dir.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+/// Returns the config file path: `NETERO_CONFIG`, then `$XDG_CONFIG_HOME/netero/config.toml`,
+/// then `~/.config/netero/config.toml`.
+pub fn config_path() -> Option<PathBuf> {
+    if let Ok(value) = std::env::var("NETERO_CONFIG") {
+        let trimmed = value.trim();
+        if !trimmed.is_empty() {
+            return Some(PathBuf::from(trimmed));
+        }
+    }
+
+    if let Ok(value) = std::env::var("XDG_CONFIG_HOME") {
+        let trimmed = value.trim();
+        if !trimmed.is_empty() {
+            return Some(Path::new(trimmed).join("netero").join("config.toml"));
+        }
+    }
+
+    let home = std::env::var("HOME").ok()?;
+    Some(
+        Path::new(&home)
+            .join(".config")
+            .join("netero")
+            .join("config.toml"),
+    )
+}
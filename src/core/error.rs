@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::core::apikey::KeyError;
+use crate::core::provider::{ErrorKind, HttpError};
+
+/// Failure categories, each with its own process exit code.
+#[derive(Debug)]
+pub enum NeteroError {
+    /// Invalid config file, env var, profile or model/endpoint selection.
+    Config(String),
+    /// API key missing or rejected.
+    Auth(String),
+    /// Rate limit or quota exhausted after retrying.
+    RateLimit(String),
+    /// Connection failure, timeout or provider outage.
+    Network(String),
+    /// Unreadable stdin, missing file or a request the model cannot take.
+    Input(String),
+    /// Not a repository, nothing staged or a failing git command.
+    Git(String),
+    /// Anything else, such as an unexpected provider response.
+    Other(String),
+    /// Stdout was closed before the output was written, as in `netero ... | head`.
+    BrokenPipe,
+}
+
+impl NeteroError {
+    /// Returns the documented exit code; 2 is left to clap usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            NeteroError::Other(_) => 1,
+            NeteroError::Config(_) => 3,
+            NeteroError::Auth(_) => 4,
+            NeteroError::RateLimit(_) => 5,
+            NeteroError::Network(_) => 6,
+            NeteroError::Input(_) => 7,
+            NeteroError::Git(_) => 8,
+            NeteroError::BrokenPipe => crate::utils::BROKEN_PIPE_EXIT,
+        }
+    }
+
+    /// Classifies a failed write to stdout.
+    pub fn output(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::BrokenPipe {
+            return NeteroError::BrokenPipe;
+        }
+        NeteroError::Other(err.to_string())
+    }
+
+    /// Classifies an error returned by a provider call.
+    pub fn from_provider(err: Box<dyn Error>) -> Self {
+        let message = chain(err.as_ref());
+        if let Some(http) = err.downcast_ref::<HttpError>() {
+            return match http.kind() {
+                ErrorKind::Auth => NeteroError::Auth(message),
+                ErrorKind::ModelNotFound | ErrorKind::EndpointNotFound => {
+                    NeteroError::Config(message)
+                }
+                ErrorKind::ContextLength | ErrorKind::NoVision => NeteroError::Input(message),
+                ErrorKind::Quota | ErrorKind::RateLimit => NeteroError::RateLimit(message),
+                ErrorKind::Unavailable => NeteroError::Network(message),
+                ErrorKind::Other => NeteroError::Other(message),
+            };
+        }
+        // Raised by the sink the reply is streamed to.
+        if let Some(io) = err.downcast_ref::<io::Error>()
+            && io.kind() == io::ErrorKind::BrokenPipe
+        {
+            return NeteroError::BrokenPipe;
+        }
+        if err.is::<KeyError>() {
+            return NeteroError::Auth(message);
+        }
+        if let Some(http) = err.downcast_ref::<reqwest::Error>()
+            && (http.is_connect() || http.is_timeout() || http.is_request() || http.is_body())
+        {
+            return NeteroError::Network(message);
+        }
+        NeteroError::Other(message)
+    }
+}
+
+impl fmt::Display for NeteroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NeteroError::Config(message)
+            | NeteroError::Auth(message)
+            | NeteroError::RateLimit(message)
+            | NeteroError::Network(message)
+            | NeteroError::Input(message)
+            | NeteroError::Git(message)
+            | NeteroError::Other(message) => write!(f, "{}", message),
+            NeteroError::BrokenPipe => write!(f, "output pipe closed"),
+        }
+    }
+}
+
+impl Error for NeteroError {}
+
+/// Joins an error with its sources; reqwest keeps the useful part ("connection refused") there.
+fn chain(err: &dyn Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        let text = cause.to_string();
+        if !message.contains(&text) {
+            message.push_str(": ");
+            message.push_str(&text);
+        }
+        source = cause.source();
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_stdout_is_a_quiet_exit() {
+        let closed = || io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(matches!(
+            NeteroError::output(closed()),
+            NeteroError::BrokenPipe
+        ));
+        assert!(matches!(
+            NeteroError::from_provider(Box::new(closed())),
+            NeteroError::BrokenPipe
+        ));
+        assert_eq!(NeteroError::BrokenPipe.exit_code(), 141);
+        assert!(matches!(
+            NeteroError::output(io::Error::from(io::ErrorKind::StorageFull)),
+            NeteroError::Other(_)
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::{Certificate, Client, Identity, NoProxy, Proxy};
+
+/// Network settings for the HTTP client a provider talks through.
+#[derive(Clone, Debug, Default)]
+pub struct HttpSettings {
+    /// Proxy for every request (`http://`, `https://`, `socks5://` or `socks5h://`).
+    /// Without one, `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` apply.
+    pub proxy: Option<String>,
+    /// Hosts that bypass `proxy`, comma separated as in `NO_PROXY`.
+    pub no_proxy: Option<String>,
+    /// PEM file with extra root certificates, trusted on top of the system ones.
+    pub ca_bundle: Option<PathBuf>,
+    /// PEM file with the client certificate chain, and its key unless `client_key` is set.
+    pub client_cert: Option<PathBuf>,
+    /// PEM file with the private key for `client_cert`.
+    pub client_key: Option<PathBuf>,
+    pub connect_timeout: Option<Duration>,
+    /// Longest wait for the next chunk of a response, so stalled streams also time out.
+    pub read_timeout: Option<Duration>,
+    /// Skips certificate verification; meant for lab servers only.
+    pub insecure: bool,
+    /// Unix domain socket every request goes through, set by a `unix://` endpoint.
+    pub unix_socket: Option<PathBuf>,
+}
+
+impl HttpSettings {
+    /// Builds the client shared by the blocking and streaming calls of a provider.
+    pub fn client(&self) -> Result<Client, String> {
+        let mut builder = Client::builder();
+
+        if let Some(url) = &self.proxy {
+            let proxy = Proxy::all(url).map_err(|err| format!("proxy {}: {}", url, err))?;
+            let proxy = proxy.no_proxy(self.no_proxy.as_deref().and_then(NoProxy::from_string));
+            builder = builder.proxy(proxy);
+        }
+        if let Some(path) = &self.ca_bundle {
+            let certs = Certificate::from_pem_bundle(&read(path)?)
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+            if certs.is_empty() {
+                return Err(format!("{}: no PEM certificates found", path.display()));
+            }
+            builder = builder.tls_certs_merge(certs);
+        }
+        if let Some(path) = &self.client_cert {
+            let mut pem = read(path)?;
+            if let Some(key) = &self.client_key {
+                pem.push(b'\n');
+                pem.extend(read(key)?);
+            }
+            let identity =
+                Identity::from_pem(&pem).map_err(|err| format!("{}: {}", path.display(), err))?;
+            builder = builder.identity(identity);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            builder = builder.read_timeout(timeout);
+        }
+        if self.insecure {
+            builder = builder.tls_danger_accept_invalid_certs(true);
+        }
+        if let Some(path) = &self.unix_socket {
+            #[cfg(unix)]
+            {
+                builder = builder.unix_socket(path.clone());
+            }
+            #[cfg(not(unix))]
+            return Err(format!(
+                "{}: Unix socket endpoints need a Unix system",
+                path.display()
+            ));
+        }
+
+        builder
+            .build()
+            .map_err(|err| format!("cannot build the HTTP client: {}", err))
+    }
+}
+
+/// Splits `unix:///run/llm.sock:/v1/chat/completions` into the socket path and the URL
+/// sent over it (`http://localhost/v1/chat/completions`). Returns `None` for other URLs.
+pub fn unix_endpoint(endpoint: &str) -> Option<(PathBuf, String)> {
+    let rest = endpoint.strip_prefix("unix://")?;
+    let (socket, path) = match rest.find(":/") {
+        Some(index) => (&rest[..index], &rest[index + 1..]),
+        None => (rest, "/"),
+    };
+    Some((PathBuf::from(socket), format!("http://localhost{}", path)))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_unix_endpoints() {
+        let cases = [
+            (
+                "unix:///run/llm.sock:/v1/chat/completions",
+                "/run/llm.sock",
+                "http://localhost/v1/chat/completions",
+            ),
+            ("unix:///run/llm.sock", "/run/llm.sock", "http://localhost/"),
+            (
+                "unix:///run/user/1000/llm:8080.sock:/api/chat",
+                "/run/user/1000/llm:8080.sock",
+                "http://localhost/api/chat",
+            ),
+            (
+                "unix://llm.sock:/v1/models?limit=5",
+                "llm.sock",
+                "http://localhost/v1/models?limit=5",
+            ),
+        ];
+        for (endpoint, socket, url) in cases {
+            assert_eq!(
+                unix_endpoint(endpoint),
+                Some((PathBuf::from(socket), url.to_string())),
+                "{}",
+                endpoint
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_other_urls_alone() {
+        for endpoint in [
+            "http://localhost:11434/api/chat",
+            "https://api.openai.com/v1/chat/completions",
+            "/run/llm.sock",
+        ] {
+            assert_eq!(unix_endpoint(endpoint), None, "{}", endpoint);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn sends_requests_over_the_socket() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        use crate::core::provider::Backend;
+        use crate::core::{Message, Service, config};
+
+        let socket = std::env::temp_dir().join(format!("netero-http-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = tokio::net::UnixListener::bind(&socket).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            // The body is small, so the end of the JSON object ends the request.
+            while !request.ends_with(b"}") {
+                let read = stream.read(&mut buf).await.unwrap();
+                assert!(read > 0, "connection closed early");
+                request.extend_from_slice(&buf[..read]);
+            }
+            let body = r#"{"choices":[{"message":{"content":"over the socket"}}]}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+
+        let endpoint = format!("unix://{}:/v1/chat/completions", socket.display());
+        let service = Service::new(&config::for_endpoint(
+            Backend::OpenAi,
+            &endpoint,
+            "test-model",
+        ))
+        .unwrap();
+        let content = service.complete(&[Message::user("hi")]).await.unwrap();
+
+        let request = server.await.unwrap();
+        assert_eq!(content, "over the socket");
+        assert!(
+            request.starts_with("POST /v1/chat/completions HTTP/1.1\r\n"),
+            "{}",
+            request
+        );
+        assert!(
+            request.to_ascii_lowercase().contains("host: localhost"),
+            "{}",
+            request
+        );
+        let _ = std::fs::remove_file(&socket);
+    }
+}
@@ -1,6 +1,8 @@
 use clap::{Parser, Subcommand};
 
-#[derive(Parser, Debug)]
+use crate::core::provider::Params;
+
+#[derive(Parser, Clone, Debug)]
 #[command(
     name = "netero",
     author,
@@ -19,12 +21,110 @@ pub struct Cli {
     #[arg(short, long, global = true)]
     pub verbose: bool,
 
+    /// Provider profile from the config file
+    #[arg(long, global = true, env = "NETERO_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Model ID, overriding NETERO_MODEL and the profile
+    #[arg(short, long, global = true, value_name = "ID")]
+    pub model: Option<String>,
+
+    /// Ignore the response cache for this run
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    pub no_cache: bool,
+
+    /// Skip cached answers but store the fresh one
+    #[arg(long, global = true)]
+    pub refresh: bool,
+
+    /// Print the reply as it is generated
+    #[arg(long, global = true, conflicts_with = "no_stream")]
+    pub stream: bool,
+
+    /// Wait for the full reply before printing it
+    #[arg(long, global = true)]
+    pub no_stream: bool,
+
+    /// Let the model read files, list directories, grep and run approved commands
+    #[arg(long, global = true)]
+    pub tools: bool,
+
+    /// Print only JSON that follows this JSON Schema (prompt mode)
+    #[arg(long, value_name = "FILE")]
+    pub schema: Option<std::path::PathBuf>,
+
+    /// Times to re-ask when the reply does not follow --schema
+    #[arg(long, value_name = "N", default_value_t = 2, requires = "schema")]
+    pub schema_retries: u32,
+
+    /// Accept invalid TLS certificates (lab servers only)
+    #[arg(long, global = true)]
+    pub insecure: bool,
+
+    /// Append every request and response to a cassette file
+    #[arg(long, global = true, value_name = "FILE", env = "NETERO_RECORD")]
+    pub record: Option<std::path::PathBuf>,
+
+    /// Answer requests from a cassette file instead of the provider
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        env = "NETERO_REPLAY",
+        conflicts_with = "record"
+    )]
+    pub replay: Option<std::path::PathBuf>,
+
     /// Start a trace server to print raw LLM traffic
     #[arg(short = 't', long)]
     pub trace: bool,
+
+    /// Sampling temperature
+    #[arg(long, global = true, value_name = "FLOAT", help_heading = "Generation")]
+    pub temperature: Option<f64>,
+
+    /// Maximum number of tokens to generate
+    #[arg(long, global = true, value_name = "N", help_heading = "Generation")]
+    pub max_tokens: Option<u32>,
+
+    /// Nucleus sampling probability mass
+    #[arg(long, global = true, value_name = "FLOAT", help_heading = "Generation")]
+    pub top_p: Option<f64>,
+
+    /// Stop sequence (repeatable)
+    #[arg(long, global = true, value_name = "SEQ", help_heading = "Generation")]
+    pub stop: Vec<String>,
+
+    /// Seed for reproducible sampling
+    #[arg(long, global = true, value_name = "N", help_heading = "Generation")]
+    pub seed: Option<u64>,
+
+    /// Presence penalty
+    #[arg(long, global = true, value_name = "FLOAT", help_heading = "Generation")]
+    pub presence_penalty: Option<f64>,
+
+    /// Frequency penalty
+    #[arg(long, global = true, value_name = "FLOAT", help_heading = "Generation")]
+    pub frequency_penalty: Option<f64>,
 }
 
-#[derive(Subcommand, Debug)]
+impl Cli {
+    /// Generation parameters given as flags.
+    pub fn params(&self) -> Params {
+        Params {
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            top_p: self.top_p,
+            stop: (!self.stop.is_empty()).then(|| self.stop.clone()),
+            seed: self.seed,
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
+            ..Params::default()
+        }
+    }
+}
+
+#[derive(Subcommand, Clone, Debug)]
 pub enum Commands {
     /// Open a minimal chat session
     Chat,
@@ -45,6 +145,41 @@ pub enum Commands {
         input: Vec<String>,
     },
 
+    /// Show the effective configuration and where each value comes from
+    Config,
+
+    /// List the models offered by the provider
+    Models {
+        /// Only show IDs containing this text (or matching it, with `*` and `?`)
+        filter: Option<String>,
+        /// Print a JSON array instead of one ID per line
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Inspect or prune the response cache
+    Cache {
+        #[command(subcommand)]
+        action: Option<CacheAction>,
+    },
+
     /// Generate shell completion
-    Completion { shell: clap_complete::Shell },
+    Completion {
+        shell: clap_complete::Shell,
+        /// Offer the provider's models for `--model` (loads the config and asks the provider)
+        #[arg(long)]
+        with_models: bool,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum CacheAction {
+    /// List the cached answers (default)
+    List,
+    /// Remove expired answers
+    Prune {
+        /// Remove every answer, not only the expired ones
+        #[arg(long)]
+        all: bool,
+    },
 }
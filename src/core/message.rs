@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::tool::ToolCall;
+
+/// Author of a conversation turn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+    /// Result of a tool call, sent back to the model.
+    Tool,
+}
+
+impl Role {
+    /// Returns the lowercase role name used by chat APIs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+        }
+    }
+}
+
+/// Single conversation turn sent to the model.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+    /// Calls requested by an assistant turn.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Call that a tool turn answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Images attached to a user turn.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<Image>,
+}
+
+/// Image sent alongside the text of a turn.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Image {
+    /// MIME type: `image/png`, `image/jpeg` or `image/webp`.
+    pub media_type: String,
+    /// Base64-encoded file contents.
+    pub data: String,
+}
+
+impl Image {
+    /// Returns the image as a `data:` URL.
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.media_type, self.data)
+    }
+}
+
+impl Message {
+    /// Builds a system instruction turn.
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::System,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            images: Vec::new(),
+        }
+    }
+
+    /// Builds a user turn.
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::User,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            images: Vec::new(),
+        }
+    }
+
+    /// Builds an assistant turn.
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            images: Vec::new(),
+        }
+    }
+
+    /// Builds an assistant turn that requests tool calls.
+    pub fn tool_request(content: impl Into<String>, calls: Vec<ToolCall>) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: content.into(),
+            tool_calls: calls,
+            tool_call_id: None,
+            images: Vec::new(),
+        }
+    }
+
+    /// Builds the turn carrying the result of `call`.
+    pub fn tool_result(call: &ToolCall, output: impl Into<String>) -> Self {
+        Self {
+            role: Role::Tool,
+            content: output.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: Some(call.id.clone()),
+            images: Vec::new(),
+        }
+    }
+
+    /// Attaches `images` to this turn.
+    pub fn with_images(mut self, images: Vec<Image>) -> Self {
+        self.images = images;
+        self
+    }
+
+    /// Name of the tool whose result this turn carries, looked up in `history`.
+    pub fn tool_name<'a>(&self, history: &'a [Message]) -> Option<&'a str> {
+        let id = self.tool_call_id.as_deref()?;
+        history
+            .iter()
+            .flat_map(|message| &message.tool_calls)
+            .find(|call| call.id == id)
+            .map(|call| call.name.as_str())
+    }
+}
+
+/// Renders a conversation as plain text, used for traces and verbose output.
+pub fn format_messages(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|message| {
+            let mut text = format!("[{}]\n{}", message.role.as_str(), message.content);
+            for image in &message.images {
+                // Base64 data would drown the text, so only the size is shown.
+                text.push_str(&format!(
+                    "\n[{} image, {} bytes]",
+                    image.media_type,
+                    image.data.len() / 4 * 3
+                ));
+            }
+            for call in &message.tool_calls {
+                text.push_str(&format!("\n-> {}({})", call.name, call.arguments));
+            }
+            text
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,334 @@
+//! Backend abstraction over chat completion APIs.
+mod anthropic;
+mod cassette;
+mod error;
+mod gemini;
+#[cfg(test)]
+pub mod mock;
+mod ollama;
+mod openai;
+mod sse;
+
+use std::str::FromStr;
+
+use futures_util::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use crate::core::tool::{ToolCall, ToolSpec};
+use crate::core::{Config, Message, http};
+
+pub use anthropic::Anthropic;
+pub use cassette::{Cassette, Recorder, Replay};
+pub use error::{Context, ErrorKind, HttpError, check};
+pub use gemini::Gemini;
+pub use ollama::Ollama;
+pub use openai::OpenAi;
+
+/// Result type shared by all provider calls.
+pub type ProviderResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Callback receiving each streamed text fragment as it arrives.
+pub type DeltaSink<'a> = dyn FnMut(&str) -> std::io::Result<()> + 'a;
+
+/// Why the model stopped generating, normalized across backends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinishReason {
+    /// Natural end of the reply or a stop sequence.
+    Stop,
+    /// `max_tokens` or the context window was reached.
+    Length,
+    /// The provider withheld the rest of the reply.
+    ContentFilter,
+    /// The model asked for a tool call.
+    ToolCalls,
+    #[serde(other)]
+    Other,
+}
+
+impl FinishReason {
+    /// Maps the reason names used by OpenAI, Anthropic, Gemini and Ollama.
+    pub fn parse(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "stop" | "end_turn" | "stop_sequence" => FinishReason::Stop,
+            "length" | "max_tokens" => FinishReason::Length,
+            "content_filter" | "safety" | "recitation" | "blocklist" | "prohibited_content"
+            | "spii" | "refusal" => FinishReason::ContentFilter,
+            "tool_calls" | "function_call" | "tool_use" => FinishReason::ToolCalls,
+            _ => FinishReason::Other,
+        }
+    }
+
+    /// Explains a reply that did not end normally, for a notice after the text.
+    pub fn warning(self) -> Option<&'static str> {
+        match self {
+            FinishReason::Length => Some("reply truncated: the token limit was reached"),
+            FinishReason::ContentFilter => Some("reply stopped by the provider's content filter"),
+            _ => None,
+        }
+    }
+}
+
+/// A model reply with the reason it ended, when the backend reports one.
+#[derive(Clone, Debug, Default)]
+pub struct Reply {
+    pub content: String,
+    pub finish_reason: Option<FinishReason>,
+    /// Tools the model asked to run before it answers.
+    pub tool_calls: Vec<ToolCall>,
+}
+
+/// Generation parameters; unset values fall back to backend defaults.
+///
+/// Backends drop the parameters their API does not support.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Params {
+    /// Sampling temperature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    /// Upper bound for generated tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Nucleus sampling probability mass.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    /// Sequences that end the generation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    /// Seed for reproducible sampling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    /// Context window size (Ollama `options.num_ctx`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    /// How long Ollama keeps the model loaded after a request (e.g. "5m", "0").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+}
+
+impl Params {
+    /// Names accepted by `set`, in display order.
+    pub const KEYS: [&'static str; 9] = [
+        "temperature",
+        "max_tokens",
+        "top_p",
+        "stop",
+        "seed",
+        "presence_penalty",
+        "frequency_penalty",
+        "num_ctx",
+        "keep_alive",
+    ];
+
+    /// Overrides every value that is set in `other`.
+    pub fn merge(&mut self, other: &Params) {
+        let mut map = self.object();
+        map.extend(other.object());
+        if let Ok(merged) = serde_json::from_value(serde_json::Value::Object(map)) {
+            *self = merged;
+        }
+    }
+
+    /// Sets a parameter from its text form (`0.2`, `512`, `["###"]`, `###`).
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if !Self::KEYS.contains(&key) {
+            return Err(format!("unknown parameter: {}", key));
+        }
+
+        let text = serde_json::Value::String(value.to_string());
+        let parsed = serde_json::from_str(value).unwrap_or(text.clone());
+        // A bare stop sequence is the common case.
+        let wrap = |value: serde_json::Value| match (key, value) {
+            ("stop", value @ serde_json::Value::String(_)) => serde_json::Value::Array(vec![value]),
+            (_, value) => value,
+        };
+
+        let mut first_err = None;
+        for candidate in [wrap(parsed), wrap(text)] {
+            let mut map = self.object();
+            map.insert(key.to_string(), candidate);
+            match serde_json::from_value(serde_json::Value::Object(map)) {
+                Ok(params) => {
+                    *self = params;
+                    return Ok(());
+                }
+                Err(err) => {
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+        Err(format!(
+            "invalid value for {}: {}",
+            key,
+            first_err.map(|err| err.to_string()).unwrap_or_default()
+        ))
+    }
+
+    /// Clears a parameter so the backend default applies again.
+    pub fn unset(&mut self, key: &str) -> Result<(), String> {
+        if !Self::KEYS.contains(&key) {
+            return Err(format!("unknown parameter: {}", key));
+        }
+        let mut map = self.object();
+        map.remove(key);
+        if let Ok(params) = serde_json::from_value(serde_json::Value::Object(map)) {
+            *self = params;
+        }
+        Ok(())
+    }
+
+    /// Returns the set parameters as `(name, JSON value)` pairs.
+    pub fn entries(&self) -> Vec<(String, serde_json::Value)> {
+        self.object().into_iter().collect()
+    }
+
+    /// Returns the names of the set parameters.
+    pub fn keys(&self) -> Vec<String> {
+        self.entries().into_iter().map(|(key, _)| key).collect()
+    }
+
+    fn object(&self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        }
+    }
+}
+
+/// Backend-neutral completion request.
+pub struct Request<'a> {
+    pub model: &'a str,
+    pub messages: &'a [Message],
+    pub params: &'a Params,
+    /// Tools the model may call; empty for plain completions.
+    pub tools: &'a [ToolSpec],
+    /// JSON Schema the reply must follow, for backends with a structured-output mode.
+    pub schema: Option<&'a serde_json::Value>,
+}
+
+impl Request<'_> {
+    /// Id for the `index`th call of the reply, for backends that do not name calls.
+    /// Numbering continues from the calls already in the conversation, so results can
+    /// still be matched to calls by id.
+    pub fn call_id(&self, index: usize) -> String {
+        let earlier: usize = self
+            .messages
+            .iter()
+            .map(|message| message.tool_calls.len())
+            .sum();
+        format!("call_{}", earlier + index)
+    }
+}
+
+/// Chat API implementation that `Service` delegates to.
+pub trait Provider {
+    /// Sends a request and waits for the full assistant reply.
+    fn complete<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>>;
+
+    /// Sends a request in streaming mode, feeding each fragment to `on_delta`.
+    /// Returns the full collected text and why generation stopped.
+    fn stream<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+        on_delta: &'a mut DeltaSink<'_>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>>;
+
+    /// Lists the model identifiers exposed by the backend.
+    fn list_models(&self) -> LocalBoxFuture<'_, ProviderResult<Vec<String>>>;
+}
+
+/// Supported backend APIs, selected by name in config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// OpenAI-compatible `/chat/completions` (codestral, most gateways).
+    OpenAi,
+    /// Anthropic Messages API (`/v1/messages`).
+    Anthropic,
+    /// Ollama native API (`/api/chat`) with NDJSON streaming.
+    Ollama,
+    /// Google Gemini `generateContent` API; the endpoint is the API base URL.
+    Gemini,
+}
+
+impl Backend {
+    /// Returns the config name of the backend.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::OpenAi => "openai",
+            Backend::Anthropic => "anthropic",
+            Backend::Ollama => "ollama",
+            Backend::Gemini => "gemini",
+        }
+    }
+
+    /// Returns the public endpoint used when no URL is configured.
+    pub fn default_endpoint(&self) -> &'static str {
+        match self {
+            Backend::OpenAi => "https://codestral.mistral.ai/v1/chat/completions",
+            Backend::Anthropic => "https://api.anthropic.com/v1/messages",
+            Backend::Ollama => "http://localhost:11434/api/chat",
+            Backend::Gemini => "https://generativelanguage.googleapis.com/v1beta",
+        }
+    }
+
+    /// Returns the vendor env var checked when `NETERO_API_KEY` is unset.
+    pub fn key_var(&self) -> &'static str {
+        match self {
+            Backend::OpenAi => "CODE_API_KEY",
+            Backend::Anthropic => "ANTHROPIC_API_KEY",
+            Backend::Ollama => "OLLAMA_API_KEY",
+            Backend::Gemini => "GEMINI_API_KEY",
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "openai" | "openai-compatible" => Ok(Backend::OpenAi),
+            "anthropic" => Ok(Backend::Anthropic),
+            "ollama" => Ok(Backend::Ollama),
+            "gemini" | "google" => Ok(Backend::Gemini),
+            other => Err(format!("unknown backend: {}", other)),
+        }
+    }
+}
+
+/// Builds the provider selected by the configuration, recording or replaying its traffic
+/// when a cassette is set.
+pub fn build(config: &Config) -> Result<Box<dyn Provider>, String> {
+    if let Some(Cassette::Replay(path)) = &config.cassette {
+        return Ok(Box::new(Replay::new(path.clone())));
+    }
+    // A `unix://` endpoint talks plain HTTP over the socket instead of TCP.
+    let mut settings = config.http.clone();
+    let endpoint = match http::unix_endpoint(&config.endpoint) {
+        Some((socket, url)) => {
+            settings.unix_socket = Some(socket);
+            url
+        }
+        None => config.endpoint.clone(),
+    };
+    // One client per provider, shared by its blocking and streaming calls.
+    let http = settings.client()?;
+    let provider: Box<dyn Provider> = match config.backend {
+        Backend::OpenAi => Box::new(OpenAi::new(http, endpoint, config.apikey.clone())),
+        Backend::Anthropic => Box::new(Anthropic::new(http, endpoint, config.apikey.clone())),
+        Backend::Ollama => Box::new(Ollama::new(http, endpoint, config.apikey.clone())),
+        Backend::Gemini => Box::new(Gemini::new(http, endpoint, config.apikey.clone())),
+    };
+    Ok(match &config.cassette {
+        Some(Cassette::Record(path)) => Box::new(Recorder::new(provider, path.clone())),
+        _ => provider,
+    })
+}
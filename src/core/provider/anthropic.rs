@@ -0,0 +1,599 @@
+use futures_util::future::LocalBoxFuture;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{DeltaSink, FinishReason, Provider, ProviderResult, Reply, Request, check, sse};
+use crate::core::apikey::{ApiKey, KeyError};
+use crate::core::message::Role;
+use crate::core::tool::ToolCall;
+
+/// API version sent in the `anthropic-version` header.
+const API_VERSION: &str = "2023-06-01";
+
+/// Output budget used when the request does not set one (the API requires it).
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Anthropic Messages API backend (`/v1/messages`).
+pub struct Anthropic {
+    http: Client,
+    endpoint: String,
+    apikey: Option<ApiKey>,
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<Turn<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Tool<'a>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<&'a [String]>,
+}
+
+#[derive(Serialize)]
+struct Turn<'a> {
+    role: &'static str,
+    content: TurnContent<'a>,
+}
+
+/// Plain text, or blocks once tool calls or images are involved.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum TurnContent<'a> {
+    Text(&'a str),
+    Blocks(Vec<Block<'a>>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Block<'a> {
+    Text {
+        text: &'a str,
+    },
+    Image {
+        source: ImageSource<'a>,
+    },
+    ToolUse {
+        id: &'a str,
+        name: &'a str,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: &'a str,
+        content: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+struct ImageSource<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    media_type: &'a str,
+    data: &'a str,
+}
+
+#[derive(Serialize)]
+struct Tool<'a> {
+    name: &'a str,
+    description: &'a str,
+    input_schema: &'a serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+    /// Set on `tool_use` blocks, along with `name` and `input`.
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    input: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+impl Anthropic {
+    pub fn new(http: Client, endpoint: String, apikey: Option<ApiKey>) -> Self {
+        Self {
+            http,
+            endpoint,
+            apikey,
+        }
+    }
+
+    async fn authorize(&self, req: RequestBuilder) -> Result<RequestBuilder, KeyError> {
+        let req = req.header("anthropic-version", API_VERSION);
+        match &self.apikey {
+            Some(key) => {
+                let key = key.value().await?;
+                Ok(req.header("x-api-key", key))
+            }
+            None => Ok(req),
+        }
+    }
+
+    /// Maps the conversation to a top-level `system` plus user/assistant turns.
+    /// The API has no seed, penalties or JSON mode, so those are not sent.
+    fn body<'a>(request: &'a Request<'a>, stream: bool) -> MessagesRequest<'a> {
+        let system = request
+            .messages
+            .iter()
+            .filter(|message| message.role == Role::System)
+            .map(|message| message.content.as_str())
+            .collect::<Vec<_>>();
+        let mut messages: Vec<Turn<'a>> = Vec::new();
+        for message in request.messages {
+            match message.role {
+                Role::System => {}
+                // Tool results go back in a user turn; results of one round share a turn.
+                Role::Tool => {
+                    let block = Block::ToolResult {
+                        tool_use_id: message.tool_call_id.as_deref().unwrap_or(""),
+                        content: &message.content,
+                    };
+                    match messages.last_mut() {
+                        Some(Turn {
+                            role: "user",
+                            content: TurnContent::Blocks(blocks),
+                        }) if matches!(blocks.first(), Some(Block::ToolResult { .. })) => {
+                            blocks.push(block)
+                        }
+                        _ => messages.push(Turn {
+                            role: "user",
+                            content: TurnContent::Blocks(vec![block]),
+                        }),
+                    }
+                }
+                _ if !message.tool_calls.is_empty() => {
+                    let mut blocks = Vec::new();
+                    if !message.content.is_empty() {
+                        blocks.push(Block::Text {
+                            text: &message.content,
+                        });
+                    }
+                    for call in &message.tool_calls {
+                        blocks.push(Block::ToolUse {
+                            id: &call.id,
+                            name: &call.name,
+                            input: call.args().unwrap_or_else(|_| serde_json::json!({})),
+                        });
+                    }
+                    messages.push(Turn {
+                        role: message.role.as_str(),
+                        content: TurnContent::Blocks(blocks),
+                    });
+                }
+                // Images go first, as the API recommends.
+                _ if !message.images.is_empty() => {
+                    let mut blocks: Vec<Block<'a>> = message
+                        .images
+                        .iter()
+                        .map(|image| Block::Image {
+                            source: ImageSource {
+                                kind: "base64",
+                                media_type: &image.media_type,
+                                data: &image.data,
+                            },
+                        })
+                        .collect();
+                    blocks.push(Block::Text {
+                        text: &message.content,
+                    });
+                    messages.push(Turn {
+                        role: message.role.as_str(),
+                        content: TurnContent::Blocks(blocks),
+                    });
+                }
+                _ => messages.push(Turn {
+                    role: message.role.as_str(),
+                    content: TurnContent::Text(&message.content),
+                }),
+            }
+        }
+        let tools = request
+            .tools
+            .iter()
+            .map(|tool| Tool {
+                name: &tool.name,
+                description: &tool.description,
+                input_schema: &tool.parameters,
+            })
+            .collect();
+
+        MessagesRequest {
+            model: request.model,
+            max_tokens: request.params.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            system: if system.is_empty() {
+                None
+            } else {
+                Some(system.join("\n\n"))
+            },
+            messages,
+            tools,
+            stream,
+            temperature: request.params.temperature,
+            top_p: request.params.top_p,
+            stop_sequences: request.params.stop.as_deref(),
+        }
+    }
+
+    /// Derives the `/v1/models` URL from the messages endpoint.
+    fn models_url(&self) -> String {
+        let base = self
+            .endpoint
+            .trim_end_matches('/')
+            .trim_end_matches("/messages");
+        format!("{}/models", base)
+    }
+}
+
+impl Provider for Anthropic {
+    fn complete<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let body = Self::body(request, false);
+            let req = self
+                .authorize(self.http.post(&self.endpoint).json(&body))
+                .await?;
+            let response = check(req.send().await?)
+                .await?
+                .json::<MessagesResponse>()
+                .await?;
+
+            // Join every text block and collect the tool calls; other block types carry
+            // no output.
+            let mut reply = Reply {
+                finish_reason: response.stop_reason.as_deref().map(FinishReason::parse),
+                ..Reply::default()
+            };
+            for block in response.content {
+                match block.kind.as_str() {
+                    "text" => reply.content.push_str(&block.text),
+                    "tool_use" => reply.tool_calls.push(ToolCall {
+                        id: block.id,
+                        name: block.name,
+                        arguments: block.input.to_string(),
+                    }),
+                    _ => {}
+                }
+            }
+            Ok(reply)
+        })
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+        on_delta: &'a mut DeltaSink<'_>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let body = Self::body(request, true);
+            let req = self
+                .authorize(self.http.post(&self.endpoint).json(&body))
+                .await?;
+            let response = check(req.send().await?).await?;
+            let mut reply = Reply::default();
+
+            sse::read(response, |event| {
+                // Every data payload repeats the event name in its `type` field.
+                let data: serde_json::Value = serde_json::from_str(&event.data)?;
+                if let Some(message) = event.error(&data) {
+                    return Err(format!("stream error: {}", message).into());
+                }
+                match data["type"].as_str().unwrap_or("") {
+                    "content_block_start" => {
+                        let block = &data["content_block"];
+                        if block["type"] == "tool_use" {
+                            reply.tool_calls.push(ToolCall {
+                                id: block["id"].as_str().unwrap_or("").to_string(),
+                                name: block["name"].as_str().unwrap_or("").to_string(),
+                                arguments: String::new(),
+                            });
+                        }
+                    }
+                    // Blocks are streamed one after another, so input fragments belong to
+                    // the last tool call started.
+                    "content_block_delta" if data["delta"]["type"] == "input_json_delta" => {
+                        if let (Some(call), Some(json)) = (
+                            reply.tool_calls.last_mut(),
+                            data["delta"]["partial_json"].as_str(),
+                        ) {
+                            call.arguments.push_str(json);
+                        }
+                    }
+                    "content_block_delta" => {
+                        let delta = data["delta"]["text"].as_str().unwrap_or("");
+                        if !delta.is_empty() {
+                            reply.content.push_str(delta);
+                            on_delta(delta)?;
+                        }
+                    }
+                    "message_delta" => {
+                        if let Some(reason) = data["delta"]["stop_reason"].as_str() {
+                            reply.finish_reason = Some(FinishReason::parse(reason));
+                        }
+                    }
+                    "message_stop" => return Ok(true),
+                    _ => {}
+                }
+                Ok(false)
+            })
+            .await?;
+            Ok(reply)
+        })
+    }
+
+    fn list_models(&self) -> LocalBoxFuture<'_, ProviderResult<Vec<String>>> {
+        Box::pin(async move {
+            let req = self.authorize(self.http.get(self.models_url())).await?;
+            let list = check(req.send().await?).await?.json::<ModelList>().await?;
+            Ok(list.data.into_iter().map(|entry| entry.id).collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::super::mock::{Canned, MockServer, client};
+    use super::*;
+    use crate::core::Message;
+    use crate::core::provider::{ErrorKind, HttpError, Params};
+    use crate::core::tool::ToolSpec;
+
+    fn backend(server: &MockServer) -> Anthropic {
+        Anthropic::new(
+            client(),
+            format!("{}/v1/messages", server.url),
+            Some(ApiKey::Value("sk-test".to_string())),
+        )
+    }
+
+    fn tools() -> Vec<ToolSpec> {
+        vec![ToolSpec {
+            name: "read_file".to_string(),
+            description: "Read a file.".to_string(),
+            parameters: json!({ "type": "object" }),
+        }]
+    }
+
+    #[tokio::test]
+    async fn complete_joins_text_and_collects_tool_calls() {
+        let server = MockServer::start(vec![Canned::json(
+            200,
+            json!({
+                "content": [
+                    { "type": "text", "text": "Let me look." },
+                    { "type": "tool_use", "id": "toolu_1", "name": "read_file",
+                      "input": { "path": "Cargo.toml" } },
+                ],
+                "stop_reason": "tool_use",
+            }),
+        )])
+        .await;
+        let messages = [Message::system("Be brief."), Message::user("What is this?")];
+        let params = Params {
+            max_tokens: Some(100),
+            ..Params::default()
+        };
+        let tools = tools();
+        let request = Request {
+            model: "claude-test",
+            messages: &messages,
+            params: &params,
+            tools: &tools,
+            schema: None,
+        };
+
+        let reply = backend(&server).complete(&request).await.unwrap();
+
+        assert_eq!(reply.content, "Let me look.");
+        assert_eq!(reply.finish_reason, Some(FinishReason::ToolCalls));
+        assert_eq!(reply.tool_calls.len(), 1);
+        assert_eq!(reply.tool_calls[0].id, "toolu_1");
+        assert_eq!(reply.tool_calls[0].name, "read_file");
+        assert_eq!(
+            reply.tool_calls[0].args().unwrap(),
+            json!({ "path": "Cargo.toml" })
+        );
+
+        let sent = &server.requests()[0];
+        assert_eq!(sent.path, "/v1/messages");
+        assert_eq!(sent.header("x-api-key"), Some("sk-test"));
+        assert_eq!(sent.header("anthropic-version"), Some(API_VERSION));
+        let body = sent.json();
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(body["max_tokens"], 100);
+        assert_eq!(
+            body["messages"],
+            json!([{ "role": "user", "content": "What is this?" }])
+        );
+        assert_eq!(body["tools"][0]["name"], "read_file");
+        assert_eq!(body.get("stream"), None);
+    }
+
+    #[tokio::test]
+    async fn complete_sends_tool_results_in_one_user_turn() {
+        let server = MockServer::start(vec![Canned::json(
+            200,
+            json!({ "content": [{ "type": "text", "text": "Done." }], "stop_reason": "end_turn" }),
+        )])
+        .await;
+        let calls = vec![
+            ToolCall {
+                id: "toolu_1".to_string(),
+                name: "read_file".to_string(),
+                arguments: r#"{"path":"a"}"#.to_string(),
+            },
+            ToolCall {
+                id: "toolu_2".to_string(),
+                name: "read_file".to_string(),
+                arguments: r#"{"path":"b"}"#.to_string(),
+            },
+        ];
+        let messages = [
+            Message::user("Compare a and b."),
+            Message::tool_request("", calls.clone()),
+            Message::tool_result(&calls[0], "A"),
+            Message::tool_result(&calls[1], "B"),
+        ];
+        let request = Request {
+            model: "claude-test",
+            messages: &messages,
+            params: &Params::default(),
+            tools: &[],
+            schema: None,
+        };
+
+        let reply = backend(&server).complete(&request).await.unwrap();
+
+        assert_eq!(reply.content, "Done.");
+        assert_eq!(reply.finish_reason, Some(FinishReason::Stop));
+        let body = server.requests()[0].json();
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(
+            body["messages"][1],
+            json!({ "role": "assistant", "content": [
+                { "type": "tool_use", "id": "toolu_1", "name": "read_file", "input": { "path": "a" } },
+                { "type": "tool_use", "id": "toolu_2", "name": "read_file", "input": { "path": "b" } },
+            ]})
+        );
+        assert_eq!(
+            body["messages"][2],
+            json!({ "role": "user", "content": [
+                { "type": "tool_result", "tool_use_id": "toolu_1", "content": "A" },
+                { "type": "tool_result", "tool_use_id": "toolu_2", "content": "B" },
+            ]})
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_emits_text_and_assembles_tool_input() {
+        let server = MockServer::start(vec![Canned::events(concat!(
+            "event: message_start\n",
+            r#"data: {"type":"message_start","message":{}}"#,
+            "\n\nevent: content_block_start\n",
+            r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            "\n\n: keep-alive\n\nevent: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hel"}}"#,
+            "\n\nevent: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"lo"}}"#,
+            "\n\nevent: content_block_start\n",
+            r#"data: {"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_9","name":"read_file","input":{}}}"#,
+            "\n\nevent: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"path\":"}}"#,
+            "\n\nevent: content_block_delta\n",
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"x\"}"}}"#,
+            "\n\nevent: message_delta\n",
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"tool_use"}}"#,
+            "\n\nevent: message_stop\n",
+            r#"data: {"type":"message_stop"}"#,
+            "\n\n",
+        ))])
+        .await;
+        let messages = [Message::user("Hi")];
+        let request = Request {
+            model: "claude-test",
+            messages: &messages,
+            params: &Params::default(),
+            tools: &[],
+            schema: None,
+        };
+        let mut deltas = Vec::new();
+        let mut sink = |delta: &str| {
+            deltas.push(delta.to_string());
+            Ok(())
+        };
+
+        let reply = backend(&server).stream(&request, &mut sink).await.unwrap();
+
+        assert_eq!(deltas, ["Hel", "lo"]);
+        assert_eq!(reply.content, "Hello");
+        assert_eq!(reply.finish_reason, Some(FinishReason::ToolCalls));
+        assert_eq!(reply.tool_calls.len(), 1);
+        assert_eq!(reply.tool_calls[0].id, "toolu_9");
+        assert_eq!(reply.tool_calls[0].args().unwrap(), json!({ "path": "x" }));
+        assert_eq!(server.requests()[0].json()["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn stream_reports_error_events() {
+        let server = MockServer::start(vec![Canned::events(concat!(
+            "event: error\n",
+            r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            "\n\n",
+        ))])
+        .await;
+        let messages = [Message::user("Hi")];
+        let request = Request {
+            model: "claude-test",
+            messages: &messages,
+            params: &Params::default(),
+            tools: &[],
+            schema: None,
+        };
+
+        let err = backend(&server)
+            .stream(&request, &mut |_| Ok(()))
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "stream error: Overloaded");
+    }
+
+    #[tokio::test]
+    async fn error_status_becomes_http_error() {
+        let server = MockServer::start(vec![Canned::json(
+            401,
+            json!({ "type": "error", "error": {
+                "type": "authentication_error", "message": "invalid x-api-key" } }),
+        )])
+        .await;
+        let messages = [Message::user("Hi")];
+        let request = Request {
+            model: "claude-test",
+            messages: &messages,
+            params: &Params::default(),
+            tools: &[],
+            schema: None,
+        };
+
+        let err = backend(&server).complete(&request).await.unwrap_err();
+        let http = err.downcast_ref::<HttpError>().expect("an HttpError");
+
+        assert_eq!(http.status.as_u16(), 401);
+        assert_eq!(http.message, "invalid x-api-key");
+        assert_eq!(http.kind(), ErrorKind::Auth);
+    }
+}
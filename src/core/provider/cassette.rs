@@ -0,0 +1,482 @@
+use std::cell::RefCell;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use futures_util::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+use super::{DeltaSink, FinishReason, Params, Provider, ProviderResult, Reply, Request};
+use crate::core::Message;
+use crate::core::message::Role;
+use crate::core::tool::ToolCall;
+
+/// Where recorded traffic goes, or comes from.
+#[derive(Clone, Debug)]
+pub enum Cassette {
+    /// Forward requests to the backend and append every exchange to the file.
+    Record(PathBuf),
+    /// Answer requests from the file without touching the network.
+    Replay(PathBuf),
+}
+
+/// One recorded exchange; a cassette is a JSON Lines file of these.
+#[derive(Clone, Deserialize, Serialize)]
+struct Exchange {
+    kind: Kind,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    model: String,
+    #[serde(default)]
+    params: Params,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    messages: Vec<Message>,
+    /// Fragments in arrival order, for streamed exchanges.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    chunks: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    response: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    finish_reason: Option<FinishReason>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    models: Vec<String>,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Complete,
+    Stream,
+    Models,
+}
+
+impl Exchange {
+    fn new(kind: Kind, request: &Request<'_>) -> Self {
+        Self {
+            kind,
+            model: request.model.to_string(),
+            params: request.params.clone(),
+            messages: request.messages.to_vec(),
+            chunks: Vec::new(),
+            response: String::new(),
+            finish_reason: None,
+            tool_calls: Vec::new(),
+            models: Vec::new(),
+        }
+    }
+
+    /// Stores the reply, keeping the fragments already set for streamed exchanges.
+    fn set_reply(&mut self, reply: &Reply) {
+        self.response = reply.content.clone();
+        self.finish_reason = reply.finish_reason;
+        self.tool_calls = reply.tool_calls.clone();
+    }
+
+    fn reply(self) -> Reply {
+        Reply {
+            content: self.response,
+            finish_reason: self.finish_reason,
+            tool_calls: self.tool_calls,
+        }
+    }
+
+    fn is_request(&self, request: &Request<'_>) -> bool {
+        self.kind != Kind::Models
+            && self.model == request.model
+            && self.params == *request.params
+            && self.messages == request.messages
+    }
+
+    /// Same request apart from system messages, which may embed the date or user name.
+    fn is_similar(&self, request: &Request<'_>) -> bool {
+        let turns = |messages: &[Message]| {
+            messages
+                .iter()
+                .filter(|message| message.role != Role::System)
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        self.kind != Kind::Models
+            && self.model == request.model
+            && self.params == *request.params
+            && turns(&self.messages) == turns(request.messages)
+    }
+}
+
+/// Wraps a backend and appends every successful exchange to a cassette.
+pub struct Recorder {
+    inner: Box<dyn Provider>,
+    path: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(inner: Box<dyn Provider>, path: PathBuf) -> Self {
+        Self { inner, path }
+    }
+
+    /// Appends one exchange, so earlier ones survive a crash or a later failure.
+    fn append(&self, exchange: &Exchange) -> ProviderResult<()> {
+        let mut line = serde_json::to_string(exchange)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|err| format!("cassette {}: {}", self.path.display(), err))?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+impl Provider for Recorder {
+    fn complete<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let reply = self.inner.complete(request).await?;
+            let mut exchange = Exchange::new(Kind::Complete, request);
+            exchange.set_reply(&reply);
+            self.append(&exchange)?;
+            Ok(reply)
+        })
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+        on_delta: &'a mut DeltaSink<'_>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let mut chunks = Vec::new();
+            let reply = {
+                let mut sink = |delta: &str| {
+                    chunks.push(delta.to_string());
+                    on_delta(delta)
+                };
+                self.inner.stream(request, &mut sink).await?
+            };
+            let mut exchange = Exchange::new(Kind::Stream, request);
+            exchange.chunks = chunks;
+            exchange.set_reply(&reply);
+            self.append(&exchange)?;
+            Ok(reply)
+        })
+    }
+
+    fn list_models(&self) -> LocalBoxFuture<'_, ProviderResult<Vec<String>>> {
+        Box::pin(async move {
+            let models = self.inner.list_models().await?;
+            self.append(&Exchange {
+                kind: Kind::Models,
+                model: String::new(),
+                params: Params::default(),
+                messages: Vec::new(),
+                chunks: Vec::new(),
+                response: String::new(),
+                finish_reason: None,
+                tool_calls: Vec::new(),
+                models: models.clone(),
+            })?;
+            Ok(models)
+        })
+    }
+}
+
+/// Serves requests from a cassette instead of a backend.
+///
+/// Each request takes the first unused exchange that matches it exactly, then one that
+/// differs only in system messages; once all matches are used, the last one is repeated.
+pub struct Replay {
+    path: PathBuf,
+    /// Exchanges read on first use, with whether each was served already.
+    tape: RefCell<Option<Vec<(Exchange, bool)>>>,
+}
+
+impl Replay {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            tape: RefCell::new(None),
+        }
+    }
+
+    /// Runs `pick` over the exchanges, loading the cassette first if needed.
+    fn with_tape<T>(
+        &self,
+        pick: impl FnOnce(&mut Vec<(Exchange, bool)>) -> Option<T>,
+    ) -> ProviderResult<T> {
+        let mut tape = self.tape.borrow_mut();
+        if tape.is_none() {
+            *tape = Some(self.load()?);
+        }
+        let exchanges = tape.as_mut().ok_or("cassette not loaded")?;
+        pick(exchanges).ok_or_else(|| {
+            format!(
+                "cassette {}: no recorded exchange matches this request",
+                self.path.display()
+            )
+            .into()
+        })
+    }
+
+    fn load(&self) -> ProviderResult<Vec<(Exchange, bool)>> {
+        let text = fs::read_to_string(&self.path)
+            .map_err(|err| format!("cassette {}: {}", self.path.display(), err))?;
+        let mut exchanges = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange = serde_json::from_str(line).map_err(|err| {
+                format!("cassette {}:{}: {}", self.path.display(), index + 1, err)
+            })?;
+            exchanges.push((exchange, false));
+        }
+        Ok(exchanges)
+    }
+
+    /// Takes the best match for `request`, preferring exchanges of the same kind.
+    fn take(&self, request: &Request<'_>, kind: Kind) -> ProviderResult<Exchange> {
+        self.with_tape(|exchanges| {
+            let tests: [&dyn Fn(&Exchange) -> bool; 3] = [
+                &|each| each.kind == kind && each.is_request(request),
+                &|each| each.is_request(request),
+                &|each| each.is_similar(request),
+            ];
+            let index = tests.iter().find_map(|test| {
+                exchanges
+                    .iter()
+                    .position(|(each, used)| !used && test(each))
+                    .or_else(|| exchanges.iter().rposition(|(each, _)| test(each)))
+            })?;
+            let (exchange, used) = &mut exchanges[index];
+            *used = true;
+            Some(exchange.clone())
+        })
+    }
+}
+
+impl Provider for Replay {
+    fn complete<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move { Ok(self.take(request, Kind::Complete)?.reply()) })
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+        on_delta: &'a mut DeltaSink<'_>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let exchange = self.take(request, Kind::Stream)?;
+            // A non-streamed recording replays as a single fragment.
+            if exchange.chunks.is_empty() && !exchange.response.is_empty() {
+                on_delta(&exchange.response)?;
+            }
+            for chunk in &exchange.chunks {
+                on_delta(chunk)?;
+            }
+            Ok(exchange.reply())
+        })
+    }
+
+    fn list_models(&self) -> LocalBoxFuture<'_, ProviderResult<Vec<String>>> {
+        Box::pin(async move {
+            self.with_tape(|exchanges| {
+                exchanges
+                    .iter()
+                    .rev()
+                    .find(|(each, _)| each.kind == Kind::Models)
+                    .map(|(each, _)| each.models.clone())
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::super::Backend;
+    use super::super::mock::{Canned, MockServer};
+    use super::*;
+    use crate::core::{Service, config};
+
+    /// A fresh cassette path for `name`.
+    fn cassette(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "netero-cassette-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn service(endpoint: &str, cassette: Cassette) -> Service {
+        let mut config = config::for_endpoint(Backend::OpenAi, endpoint, "test-model");
+        config.cassette = Some(cassette);
+        Service::new(&config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_what_was_recorded() {
+        let server = MockServer::start(vec![
+            Canned::json(
+                200,
+                json!({ "choices": [{ "message": { "content": "Hi there" }, "finish_reason": "stop" }] }),
+            ),
+            Canned::events(
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n\
+                 data: {\"choices\":[{\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"length\"}]}\n\n\
+                 data: [DONE]\n\n",
+            ),
+        ])
+        .await;
+        let path = cassette("round-trip");
+        let messages = [Message::system("Be brief."), Message::user("Hi")];
+
+        let recorder = service(
+            &format!("{}/v1/chat/completions", server.url),
+            Cassette::Record(path.clone()),
+        );
+        let completed = recorder.complete(&messages).await.unwrap();
+        let streamed = recorder
+            .stream(&messages, &mut |_: &str| Ok(()))
+            .await
+            .unwrap();
+        assert_eq!(server.requests().len(), 2);
+
+        // Nothing listens on the discard port, so any request would fail.
+        let replay = service(
+            "http://127.0.0.1:9/v1/chat/completions",
+            Cassette::Replay(path.clone()),
+        );
+        let mut deltas = Vec::new();
+        let replayed = replay
+            .stream(&messages, &mut |delta: &str| {
+                deltas.push(delta.to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(replay.complete(&messages).await.unwrap(), completed);
+
+        assert_eq!(completed, "Hi there");
+        assert_eq!(deltas, ["Hel", "lo"]);
+        assert_eq!(replayed.content, streamed.content);
+        assert_eq!(replayed.finish_reason, Some(FinishReason::Length));
+        assert_eq!(server.requests().len(), 2);
+        let _ = fs::remove_file(&path);
+    }
+
+    fn request<'a>(messages: &'a [Message], params: &'a Params) -> Request<'a> {
+        Request {
+            model: "test-model",
+            messages,
+            params,
+            tools: &[],
+            schema: None,
+        }
+    }
+
+    /// A replay of `exchanges`, each recorded as `(kind, system prompt, response)`.
+    fn replay(name: &str, exchanges: &[(Kind, &str, &str)]) -> Replay {
+        let path = cassette(name);
+        let params = Params::default();
+        let mut lines = String::new();
+        for (kind, system, response) in exchanges {
+            let messages = [Message::system(*system), Message::user("Hi")];
+            let mut exchange = Exchange::new(*kind, &request(&messages, &params));
+            exchange.response = response.to_string();
+            lines.push_str(&serde_json::to_string(&exchange).unwrap());
+            lines.push('\n');
+        }
+        fs::write(&path, lines).unwrap();
+        Replay::new(path)
+    }
+
+    async fn ask(replay: &Replay, kind: Kind, system: &str) -> ProviderResult<String> {
+        let messages = [Message::system(system), Message::user("Hi")];
+        let params = Params::default();
+        let request = request(&messages, &params);
+        let reply = match kind {
+            Kind::Stream => replay.stream(&request, &mut |_: &str| Ok(())).await?,
+            _ => replay.complete(&request).await?,
+        };
+        Ok(reply.content)
+    }
+
+    #[tokio::test]
+    async fn prefers_exact_matches_of_the_same_kind() {
+        let tape = replay(
+            "order",
+            &[
+                (Kind::Complete, "Today is Monday.", "similar"),
+                (Kind::Stream, "Today is Tuesday.", "exact stream"),
+                (Kind::Complete, "Today is Tuesday.", "exact complete"),
+            ],
+        );
+        assert_eq!(
+            ask(&tape, Kind::Complete, "Today is Tuesday.")
+                .await
+                .unwrap(),
+            "exact complete"
+        );
+        assert_eq!(
+            ask(&tape, Kind::Stream, "Today is Tuesday.").await.unwrap(),
+            "exact stream"
+        );
+
+        let tape = replay(
+            "order-kind",
+            &[
+                (Kind::Complete, "Today is Monday.", "similar"),
+                (Kind::Stream, "Today is Tuesday.", "exact stream"),
+            ],
+        );
+        assert_eq!(
+            ask(&tape, Kind::Complete, "Today is Tuesday.")
+                .await
+                .unwrap(),
+            "exact stream"
+        );
+
+        let tape = replay(
+            "order-similar",
+            &[(Kind::Complete, "Today is Monday.", "similar")],
+        );
+        assert_eq!(
+            ask(&tape, Kind::Complete, "Today is Wednesday.")
+                .await
+                .unwrap(),
+            "similar"
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_when_nothing_matches() {
+        let tape = replay(
+            "no-match",
+            &[(Kind::Complete, "Today is Monday.", "unused")],
+        );
+        let messages = [Message::user("Bye")];
+        let params = Params::default();
+
+        let err = tape
+            .complete(&request(&messages, &params))
+            .await
+            .unwrap_err();
+
+        assert!(
+            err.to_string()
+                .contains("no recorded exchange matches this request"),
+            "{}",
+            err
+        );
+    }
+}
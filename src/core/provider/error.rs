@@ -0,0 +1,486 @@
+use std::fmt;
+use std::time::Duration;
+
+use reqwest::{Response, StatusCode};
+
+use super::Backend;
+
+/// Longest server message kept when the body is not a known error shape.
+const MAX_RAW_MESSAGE: usize = 300;
+
+/// Non-success HTTP response returned by a backend.
+#[derive(Debug)]
+pub struct HttpError {
+    pub status: StatusCode,
+    /// Delay requested by the server through `Retry-After`.
+    pub retry_after: Option<Duration>,
+    /// Error text reported by the server.
+    pub message: String,
+    /// Machine-readable error code or type (`invalid_api_key`, `not_found_error`, ...).
+    pub code: Option<String>,
+    /// What went wrong, in terms of netero's own configuration.
+    pub summary: Option<String>,
+    /// What the user can do about it.
+    pub hint: Option<String>,
+}
+
+/// What went wrong, derived from the status, error code and message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    Auth,
+    ModelNotFound,
+    EndpointNotFound,
+    ContextLength,
+    /// The model does not take image input.
+    NoVision,
+    Quota,
+    RateLimit,
+    Unavailable,
+    Other,
+}
+
+/// Request context used to explain an `HttpError`.
+pub struct Context<'a> {
+    pub backend: Backend,
+    pub model: &'a str,
+    pub endpoint: &'a str,
+    /// Where the API key came from (`NETERO_API_KEY`, `profile work`, ...), if one was sent.
+    pub key_source: Option<&'a str>,
+}
+
+impl HttpError {
+    /// Reads the status, `Retry-After` header and error body of a failed response.
+    pub async fn read(response: Response) -> Self {
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        let body = response.text().await.unwrap_or_default();
+        let (message, code) = parse_body(&body);
+        Self {
+            status,
+            retry_after,
+            message,
+            code,
+            summary: None,
+            hint: None,
+        }
+    }
+
+    /// Classifies the error; OpenAI, Anthropic, Mistral, Gemini and Ollama are covered.
+    pub fn kind(&self) -> ErrorKind {
+        let code = self.code.as_deref().unwrap_or("").to_ascii_lowercase();
+        let message = self.message.to_ascii_lowercase();
+        let mentions = |words: &[&str]| words.iter().any(|word| message.contains(word));
+
+        if matches!(
+            code.as_str(),
+            "invalid_api_key"
+                | "authentication_error"
+                | "permission_error"
+                | "unauthenticated"
+                | "permission_denied"
+        ) || matches!(
+            self.status,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) || (message.contains("api key") && mentions(&["invalid", "not valid", "incorrect"]))
+        {
+            return ErrorKind::Auth;
+        }
+        if code == "context_length_exceeded"
+            || mentions(&[
+                "context length",
+                "context window",
+                "maximum context",
+                "prompt is too long",
+                "too many tokens",
+            ])
+        {
+            return ErrorKind::ContextLength;
+        }
+        if mentions(&["image", "vision", "multimodal"])
+            && mentions(&[
+                "not support",
+                "unsupported",
+                "only supported",
+                "does not accept",
+                "missing data required",
+            ])
+        {
+            return ErrorKind::NoVision;
+        }
+        if code == "model_not_found"
+            || (message.contains("model")
+                && (self.status == StatusCode::NOT_FOUND
+                    || mentions(&[
+                        "not found",
+                        "does not exist",
+                        "invalid model",
+                        "no such model",
+                    ])))
+        {
+            return ErrorKind::ModelNotFound;
+        }
+        if self.status == StatusCode::NOT_FOUND {
+            return ErrorKind::EndpointNotFound;
+        }
+        if code == "insufficient_quota" || (code == "resource_exhausted" && mentions(&["quota"])) {
+            return ErrorKind::Quota;
+        }
+        if self.status == StatusCode::TOO_MANY_REQUESTS {
+            return ErrorKind::RateLimit;
+        }
+        if self.status.is_server_error() || self.status.as_u16() == 529 {
+            return ErrorKind::Unavailable;
+        }
+        ErrorKind::Other
+    }
+
+    /// Fills in a summary and hint phrased in terms of the user's configuration.
+    pub fn explain(&mut self, context: &Context<'_>) {
+        let (summary, hint) = match self.kind() {
+            ErrorKind::Auth => match context.key_source {
+                Some(source) => (
+                    format!("{} rejected", source),
+                    "check that the key is valid for this provider".to_string(),
+                ),
+                // Mirrors config: the vendor key only applies to the backend's own endpoint.
+                None if context.backend != Backend::OpenAi
+                    || context.endpoint == context.backend.default_endpoint() =>
+                {
+                    (
+                        "no API key configured".to_string(),
+                        format!("set NETERO_API_KEY or {}", context.backend.key_var()),
+                    )
+                }
+                None => (
+                    "no API key configured".to_string(),
+                    "set NETERO_API_KEY or `api_key_env` or `key_command` in the profile"
+                        .to_string(),
+                ),
+            },
+            ErrorKind::ModelNotFound => (
+                format!("model {} not found", context.model),
+                match context.backend {
+                    Backend::Ollama => format!("run `ollama pull {}` and retry", context.model),
+                    _ => "check NETERO_MODEL or the profile's `model`".to_string(),
+                },
+            ),
+            ErrorKind::EndpointNotFound => (
+                format!("endpoint {} not found", context.endpoint),
+                "check NETERO_URL or the profile's `endpoint`".to_string(),
+            ),
+            ErrorKind::ContextLength => (
+                format!("request exceeds the context window of {}", context.model),
+                "use /clean in chat or attach fewer files".to_string(),
+            ),
+            ErrorKind::NoVision => (
+                format!("model {} cannot read images", context.model),
+                "pick a vision-capable model with --model or a profile, or attach no images"
+                    .to_string(),
+            ),
+            ErrorKind::Quota => (
+                "quota exhausted".to_string(),
+                "check the billing or usage limits of the account".to_string(),
+            ),
+            ErrorKind::RateLimit => (
+                "rate limited by the provider".to_string(),
+                "wait a moment or raise `max_retries` in `[retry]`".to_string(),
+            ),
+            ErrorKind::Unavailable => (
+                format!("{} is unavailable", context.backend.as_str()),
+                "try again later".to_string(),
+            ),
+            ErrorKind::Other => return,
+        };
+        self.summary.get_or_insert(summary);
+        self.hint.get_or_insert(hint);
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.summary {
+            Some(summary) => write!(f, "{} ({})", summary, self.status)?,
+            None => write!(f, "{}", self.status)?,
+        }
+        if !self.message.is_empty() {
+            write!(f, ": {}", self.message)?;
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "\nhint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Passes successful responses through and turns any other status into an `HttpError`.
+pub async fn check(response: Response) -> Result<Response, HttpError> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(HttpError::read(response).await)
+    }
+}
+
+/// Extracts the message and error code from the error shapes used by the supported APIs.
+fn parse_body(body: &str) -> (String, Option<String>) {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        // Proxies and gateways often answer with HTML or plain text.
+        let text = body.trim();
+        let message = match text.char_indices().nth(MAX_RAW_MESSAGE) {
+            Some((end, _)) => format!("{}...", &text[..end]),
+            None => text.to_string(),
+        };
+        return (message, None);
+    };
+
+    // OpenAI, Anthropic and Gemini nest the details in `error`; Ollama uses a plain string.
+    let error = &value["error"];
+    let message = error["message"]
+        .as_str()
+        .or_else(|| error.as_str())
+        // Mistral: top-level `message`, or FastAPI-style `detail`.
+        .or_else(|| value["message"].as_str())
+        .or_else(|| value["detail"].as_str())
+        .or_else(|| value["detail"][0]["msg"].as_str())
+        .map(|message| message.trim().to_string())
+        .unwrap_or_else(|| body.trim().to_string());
+    let code = [
+        &error["code"],
+        &error["type"],
+        &error["status"],
+        &value["code"],
+        &value["type"],
+    ]
+    .into_iter()
+    .find_map(|value| value.as_str())
+    .filter(|code| *code != "error")
+    .map(str::to_string);
+
+    (message, code)
+}
+
+/// Parses `Retry-After` given either as seconds or as an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let seconds = (date.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(seconds as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(status: u16, body: &str) -> HttpError {
+        let (message, code) = parse_body(body);
+        HttpError {
+            status: StatusCode::from_u16(status).unwrap(),
+            retry_after: None,
+            message,
+            code,
+            summary: None,
+            hint: None,
+        }
+    }
+
+    #[test]
+    fn classifies_provider_error_bodies() {
+        let cases = [
+            // OpenAI
+            (
+                401,
+                r#"{"error":{"message":"Incorrect API key provided: sk-x.","type":"invalid_request_error","param":null,"code":"invalid_api_key"}}"#,
+                ErrorKind::Auth,
+                Some("invalid_api_key"),
+            ),
+            (
+                404,
+                r#"{"error":{"message":"The model `gpt-9` does not exist or you do not have access to it.","type":"invalid_request_error","param":null,"code":"model_not_found"}}"#,
+                ErrorKind::ModelNotFound,
+                Some("model_not_found"),
+            ),
+            (
+                400,
+                r#"{"error":{"message":"This model's maximum context length is 8192 tokens. However, your messages resulted in 9000 tokens.","type":"invalid_request_error","param":"messages","code":"context_length_exceeded"}}"#,
+                ErrorKind::ContextLength,
+                Some("context_length_exceeded"),
+            ),
+            (
+                400,
+                r#"{"error":{"message":"Invalid content type. image_url is only supported by certain models.","type":"invalid_request_error","param":"messages.[0].content.[1].type","code":null}}"#,
+                ErrorKind::NoVision,
+                Some("invalid_request_error"),
+            ),
+            (
+                429,
+                r#"{"error":{"message":"You exceeded your current quota, please check your plan and billing details.","type":"insufficient_quota","param":null,"code":"insufficient_quota"}}"#,
+                ErrorKind::Quota,
+                Some("insufficient_quota"),
+            ),
+            (
+                429,
+                r#"{"error":{"message":"Rate limit reached for requests per min.","type":"requests","param":null,"code":"rate_limit_exceeded"}}"#,
+                ErrorKind::RateLimit,
+                Some("rate_limit_exceeded"),
+            ),
+            // Anthropic
+            (
+                401,
+                r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#,
+                ErrorKind::Auth,
+                Some("authentication_error"),
+            ),
+            (
+                404,
+                r#"{"type":"error","error":{"type":"not_found_error","message":"model: claude-9"}}"#,
+                ErrorKind::ModelNotFound,
+                Some("not_found_error"),
+            ),
+            (
+                400,
+                r#"{"type":"error","error":{"type":"invalid_request_error","message":"prompt is too long: 210000 tokens > 200000 maximum"}}"#,
+                ErrorKind::ContextLength,
+                Some("invalid_request_error"),
+            ),
+            (
+                529,
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+                ErrorKind::Unavailable,
+                Some("overloaded_error"),
+            ),
+            // Mistral: top-level `message`, or `detail`
+            (
+                401,
+                r#"{"message":"Unauthorized","request_id":"5b1c"}"#,
+                ErrorKind::Auth,
+                None,
+            ),
+            (
+                400,
+                r#"{"object":"error","message":"Invalid model: mistral-9","type":"invalid_model","param":null,"code":"1500"}"#,
+                ErrorKind::ModelNotFound,
+                Some("1500"),
+            ),
+            (
+                422,
+                r#"{"detail":[{"type":"missing","loc":["body","model"],"msg":"Field required","input":{}}]}"#,
+                ErrorKind::Other,
+                None,
+            ),
+            (
+                404,
+                r#"{"detail":"Not Found"}"#,
+                ErrorKind::EndpointNotFound,
+                None,
+            ),
+            // Gemini
+            (
+                400,
+                r#"{"error":{"code":400,"message":"API key not valid. Please pass a valid API key.","status":"INVALID_ARGUMENT"}}"#,
+                ErrorKind::Auth,
+                Some("INVALID_ARGUMENT"),
+            ),
+            (
+                429,
+                r#"{"error":{"code":429,"message":"You exceeded your current quota, please check your plan and billing details.","status":"RESOURCE_EXHAUSTED"}}"#,
+                ErrorKind::Quota,
+                Some("RESOURCE_EXHAUSTED"),
+            ),
+            (
+                404,
+                r#"{"error":{"code":404,"message":"models/gemini-9 is not found for API version v1beta, or is not supported for generateContent.","status":"NOT_FOUND"}}"#,
+                ErrorKind::ModelNotFound,
+                Some("NOT_FOUND"),
+            ),
+            (
+                503,
+                r#"{"error":{"code":503,"message":"The model is overloaded. Please try again later.","status":"UNAVAILABLE"}}"#,
+                ErrorKind::Unavailable,
+                Some("UNAVAILABLE"),
+            ),
+            // Ollama: `error` is a plain string
+            (
+                404,
+                r#"{"error":"model \"llama9\" not found, try pulling it first"}"#,
+                ErrorKind::ModelNotFound,
+                None,
+            ),
+            (
+                500,
+                r#"{"error":"llama3 does not support images"}"#,
+                ErrorKind::NoVision,
+                None,
+            ),
+            // Gateways answering with HTML
+            (
+                502,
+                "<html><body>Bad Gateway</body></html>",
+                ErrorKind::Unavailable,
+                None,
+            ),
+        ];
+
+        for (status, body, kind, code) in cases {
+            let error = error(status, body);
+            assert_eq!(error.kind(), kind, "{} {}", status, body);
+            assert_eq!(error.code.as_deref(), code, "{}", body);
+            assert!(!error.message.starts_with('{'), "{}", error.message);
+        }
+    }
+
+    #[test]
+    fn keeps_the_message_from_each_shape() {
+        assert_eq!(
+            error(401, r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#).message,
+            "invalid x-api-key"
+        );
+        assert_eq!(
+            error(401, r#"{"message":"Unauthorized"}"#).message,
+            "Unauthorized"
+        );
+        assert_eq!(
+            error(422, r#"{"detail":[{"msg":"Field required"}]}"#).message,
+            "Field required"
+        );
+        assert_eq!(
+            error(404, r#"{"error":"model not found"}"#).message,
+            "model not found"
+        );
+        assert_eq!(error(502, "  Bad Gateway\n").message, "Bad Gateway");
+
+        let long = error(502, &"x".repeat(MAX_RAW_MESSAGE + 50)).message;
+        assert_eq!(long.len(), MAX_RAW_MESSAGE + 3);
+        assert!(long.ends_with("..."));
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        let wait = parse_retry_after(&later).unwrap();
+        assert!(
+            wait > Duration::from_secs(55) && wait <= Duration::from_secs(60),
+            "{:?}",
+            wait
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+
+        assert_eq!(parse_retry_after("soon"), None);
+        assert_eq!(parse_retry_after("-5"), None);
+        assert_eq!(parse_retry_after(""), None);
+    }
+}
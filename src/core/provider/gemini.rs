@@ -0,0 +1,489 @@
+use futures_util::future::LocalBoxFuture;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{DeltaSink, FinishReason, Provider, ProviderResult, Reply, Request, check, sse};
+use crate::core::apikey::{ApiKey, KeyError};
+use crate::core::message::Role;
+use crate::core::tool::{ToolCall, ToolSpec};
+
+/// Google Gemini backend (`generateContent` / `streamGenerateContent`).
+pub struct Gemini {
+    http: Client,
+    /// API base such as `https://generativelanguage.googleapis.com/v1beta`.
+    endpoint: String,
+    apikey: Option<ApiKey>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateRequest<'a> {
+    contents: Vec<Content<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<SystemInstruction>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Tools<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig<'a>>,
+}
+
+#[derive(Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_json_schema: Option<&'a serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct Content<'a> {
+    role: &'static str,
+    parts: Vec<Part<'a>>,
+}
+
+/// One of text, an inline image, a function call by the model or the response sent back
+/// for it.
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Part<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inline_data: Option<InlineData<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    function_call: Option<FunctionCall<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    function_response: Option<FunctionResponse<'a>>,
+}
+
+/// Base64 file contents with their MIME type.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InlineData<'a> {
+    mime_type: &'a str,
+    data: &'a str,
+}
+
+#[derive(Serialize)]
+struct FunctionCall<'a> {
+    name: &'a str,
+    args: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct FunctionResponse<'a> {
+    name: &'a str,
+    response: serde_json::Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Tools<'a> {
+    function_declarations: &'a [ToolSpec],
+}
+
+#[derive(Serialize)]
+struct SystemInstruction {
+    parts: [OwnedPart; 1],
+}
+
+#[derive(Serialize)]
+struct OwnedPart {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Deserialize)]
+struct Candidate {
+    #[serde(default)]
+    content: Option<CandidateContent>,
+    #[serde(default, rename = "finishReason")]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<ResponsePart>,
+}
+
+#[derive(Deserialize)]
+struct ResponsePart {
+    #[serde(default)]
+    text: String,
+    #[serde(default, rename = "functionCall")]
+    function_call: Option<ResponseCall>,
+}
+
+#[derive(Deserialize)]
+struct ResponseCall {
+    name: String,
+    #[serde(default)]
+    args: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    #[serde(default)]
+    models: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    name: String,
+}
+
+impl GenerateResponse {
+    /// Returns why the first candidate stopped, once it has.
+    fn finish_reason(&self) -> Option<FinishReason> {
+        let reason = self.candidates.first()?.finish_reason.as_deref()?;
+        Some(FinishReason::parse(reason))
+    }
+
+    /// Returns the text and function calls of the first candidate. Gemini has no call
+    /// ids, so calls are numbered from `first_call` within `request`.
+    fn reply(self, request: &Request<'_>, first_call: usize) -> Reply {
+        let mut reply = Reply {
+            finish_reason: self.finish_reason(),
+            ..Reply::default()
+        };
+        let parts = self
+            .candidates
+            .into_iter()
+            .next()
+            .and_then(|candidate| candidate.content)
+            .map(|content| content.parts)
+            .unwrap_or_default();
+        for part in parts {
+            reply.content.push_str(&part.text);
+            if let Some(call) = part.function_call {
+                reply.tool_calls.push(ToolCall {
+                    id: request.call_id(first_call + reply.tool_calls.len()),
+                    name: call.name,
+                    arguments: call.args.to_string(),
+                });
+            }
+        }
+        reply
+    }
+}
+
+impl Gemini {
+    pub fn new(http: Client, endpoint: String, apikey: Option<ApiKey>) -> Self {
+        Self {
+            http,
+            endpoint,
+            apikey,
+        }
+    }
+
+    /// Gemini authenticates with the `x-goog-api-key` header. The `key` query parameter
+    /// also works, but reqwest errors print the URL, which would leak the key.
+    async fn authorize(&self, req: RequestBuilder) -> Result<RequestBuilder, KeyError> {
+        match &self.apikey {
+            Some(key) => {
+                let key = key.value().await?;
+                Ok(req.header("x-goog-api-key", key))
+            }
+            None => Ok(req),
+        }
+    }
+
+    fn model_url(&self, model: &str, method: &str) -> String {
+        let model = model.strip_prefix("models/").unwrap_or(model);
+        format!(
+            "{}/models/{}:{}",
+            self.endpoint.trim_end_matches('/'),
+            model,
+            method
+        )
+    }
+
+    /// Maps the conversation to `contents` plus a `systemInstruction`.
+    fn body<'a>(request: &'a Request<'a>) -> GenerateRequest<'a> {
+        let system = request
+            .messages
+            .iter()
+            .filter(|message| message.role == Role::System)
+            .map(|message| message.content.as_str())
+            .collect::<Vec<_>>();
+        let mut contents: Vec<Content<'a>> = Vec::new();
+        for message in request.messages {
+            match message.role {
+                Role::System => {}
+                // Function responses are matched to calls by name; the responses of one
+                // round share a turn.
+                Role::Tool => {
+                    let part = Part {
+                        function_response: Some(FunctionResponse {
+                            name: message.tool_name(request.messages).unwrap_or(""),
+                            response: serde_json::json!({ "content": message.content }),
+                        }),
+                        ..Part::default()
+                    };
+                    match contents.last_mut() {
+                        Some(content)
+                            if content
+                                .parts
+                                .first()
+                                .is_some_and(|part| part.function_response.is_some()) =>
+                        {
+                            content.parts.push(part)
+                        }
+                        _ => contents.push(Content {
+                            role: "user",
+                            parts: vec![part],
+                        }),
+                    }
+                }
+                role => {
+                    let mut parts = Vec::new();
+                    if !message.content.is_empty() || message.tool_calls.is_empty() {
+                        parts.push(Part {
+                            text: Some(&message.content),
+                            ..Part::default()
+                        });
+                    }
+                    for image in &message.images {
+                        parts.push(Part {
+                            inline_data: Some(InlineData {
+                                mime_type: &image.media_type,
+                                data: &image.data,
+                            }),
+                            ..Part::default()
+                        });
+                    }
+                    for call in &message.tool_calls {
+                        parts.push(Part {
+                            function_call: Some(FunctionCall {
+                                name: &call.name,
+                                args: call.args().unwrap_or_else(|_| serde_json::json!({})),
+                            }),
+                            ..Part::default()
+                        });
+                    }
+                    contents.push(Content {
+                        role: if role == Role::Assistant {
+                            "model"
+                        } else {
+                            "user"
+                        },
+                        parts,
+                    });
+                }
+            }
+        }
+
+        let params = request.params;
+        let config = GenerationConfig {
+            temperature: params.temperature,
+            max_output_tokens: params.max_tokens,
+            top_p: params.top_p,
+            stop_sequences: params.stop.as_deref(),
+            seed: params.seed,
+            presence_penalty: params.presence_penalty,
+            frequency_penalty: params.frequency_penalty,
+            response_mime_type: request.schema.map(|_| "application/json"),
+            response_json_schema: request.schema,
+        };
+
+        GenerateRequest {
+            contents,
+            generation_config: (config != GenerationConfig::default()).then_some(config),
+            tools: if request.tools.is_empty() {
+                Vec::new()
+            } else {
+                vec![Tools {
+                    function_declarations: request.tools,
+                }]
+            },
+            system_instruction: if system.is_empty() {
+                None
+            } else {
+                Some(SystemInstruction {
+                    parts: [OwnedPart {
+                        text: system.join("\n\n"),
+                    }],
+                })
+            },
+        }
+    }
+}
+
+impl Provider for Gemini {
+    fn complete<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let url = self.model_url(request.model, "generateContent");
+            let req = self
+                .authorize(self.http.post(url).json(&Self::body(request)))
+                .await?;
+            let response = check(req.send().await?)
+                .await?
+                .json::<GenerateResponse>()
+                .await?;
+            Ok(response.reply(request, 0))
+        })
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+        on_delta: &'a mut DeltaSink<'_>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let url = self.model_url(request.model, "streamGenerateContent");
+            let req = self
+                .authorize(self.http.post(url))
+                .await?
+                .query(&[("alt", "sse")])
+                .json(&Self::body(request));
+            let response = check(req.send().await?).await?;
+            let mut reply = Reply::default();
+
+            sse::read(response, |event| {
+                let data: serde_json::Value = serde_json::from_str(&event.data)?;
+                if let Some(message) = event.error(&data) {
+                    return Err(format!("stream error: {}", message).into());
+                }
+                let parsed: GenerateResponse = serde_json::from_value(data)?;
+                let chunk = parsed.reply(request, reply.tool_calls.len());
+                if chunk.finish_reason.is_some() {
+                    reply.finish_reason = chunk.finish_reason;
+                }
+                if !chunk.content.is_empty() {
+                    reply.content.push_str(&chunk.content);
+                    on_delta(&chunk.content)?;
+                }
+                reply.tool_calls.extend(chunk.tool_calls);
+                Ok(false)
+            })
+            .await?;
+            Ok(reply)
+        })
+    }
+
+    fn list_models(&self) -> LocalBoxFuture<'_, ProviderResult<Vec<String>>> {
+        Box::pin(async move {
+            let url = format!("{}/models", self.endpoint.trim_end_matches('/'));
+            let req = self.authorize(self.http.get(url)).await?;
+            let list = check(req.send().await?).await?.json::<ModelList>().await?;
+            Ok(list
+                .models
+                .into_iter()
+                .map(|entry| match entry.name.strip_prefix("models/") {
+                    Some(id) => id.to_string(),
+                    None => entry.name,
+                })
+                .collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::super::mock::{Canned, MockServer, client};
+    use super::*;
+    use crate::core::Message;
+    use crate::core::provider::Params;
+
+    fn backend(server: &MockServer) -> Gemini {
+        Gemini::new(
+            client(),
+            format!("{}/v1beta", server.url),
+            Some(ApiKey::Value("secret-key".to_string())),
+        )
+    }
+
+    #[tokio::test]
+    async fn key_goes_in_a_header_not_the_url() {
+        let server = MockServer::start(vec![Canned::json(
+            200,
+            json!({ "candidates": [{
+                "content": { "parts": [{ "text": "Hi there" }] },
+                "finishReason": "STOP",
+            }]}),
+        )])
+        .await;
+        let gemini = backend(&server);
+        let messages = [Message::system("Be brief."), Message::user("Hi")];
+        let request = Request {
+            model: "gemini-test",
+            messages: &messages,
+            params: &Params::default(),
+            tools: &[],
+            schema: None,
+        };
+
+        let reply = gemini.complete(&request).await.unwrap();
+
+        assert_eq!(reply.content, "Hi there");
+        assert_eq!(reply.finish_reason, Some(FinishReason::Stop));
+        let sent = &server.requests()[0];
+        assert_eq!(sent.path, "/v1beta/models/gemini-test:generateContent");
+        assert_eq!(sent.header("x-goog-api-key"), Some("secret-key"));
+        let body = sent.json();
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief.");
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "Hi");
+    }
+
+    #[tokio::test]
+    async fn stream_forwards_deltas_and_the_finish_reason() {
+        let server = MockServer::start(vec![Canned::events(
+            "data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"Hel\"}]}}]}\n\n\
+             data: {\"candidates\":[{\"content\":{\"parts\":[{\"text\":\"lo\"}]},\"finishReason\":\"MAX_TOKENS\"}]}\n\n",
+        )])
+        .await;
+        let messages = [Message::user("Hi")];
+        let request = Request {
+            model: "gemini-test",
+            messages: &messages,
+            params: &Params::default(),
+            tools: &[],
+            schema: None,
+        };
+        let mut deltas = Vec::new();
+
+        let reply = backend(&server)
+            .stream(&request, &mut |delta: &str| {
+                deltas.push(delta.to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(deltas, ["Hel", "lo"]);
+        assert_eq!(reply.content, "Hello");
+        assert_eq!(reply.finish_reason, Some(FinishReason::Length));
+        let sent = &server.requests()[0];
+        assert_eq!(
+            sent.path,
+            "/v1beta/models/gemini-test:streamGenerateContent?alt=sse"
+        );
+        assert_eq!(sent.header("x-goog-api-key"), Some("secret-key"));
+    }
+}
@@ -0,0 +1,207 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Local HTTP server answering with canned responses, one per connection, and keeping
+/// the requests it received.
+pub struct MockServer {
+    /// Base URL, such as `http://127.0.0.1:40123`.
+    pub url: String,
+    requests: Arc<Mutex<Vec<Received>>>,
+}
+
+/// Response the server sends.
+#[derive(Clone)]
+pub struct Canned {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+    /// Wait before answering, to play a slow provider.
+    delay: Duration,
+    /// Body offsets where the server pauses, so the client reads the body in pieces.
+    breaks: Vec<usize>,
+}
+
+/// Request the server received.
+#[derive(Clone, Debug)]
+pub struct Received {
+    pub path: String,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Canned {
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: body.to_string(),
+            delay: Duration::ZERO,
+            breaks: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// `text/event-stream` body; `events` are sent as written.
+    pub fn events(events: &str) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("content-type".to_string(), "text/event-stream".to_string())],
+            body: events.to_string(),
+            delay: Duration::ZERO,
+            breaks: Vec::new(),
+        }
+    }
+
+    /// `application/x-ndjson` body sent one part at a time; parts need not end on a line.
+    pub fn ndjson(parts: &[&str]) -> Self {
+        let breaks = parts
+            .iter()
+            .scan(0, |offset, part| {
+                *offset += part.len();
+                Some(*offset)
+            })
+            .collect();
+        Self {
+            status: 200,
+            headers: vec![(
+                "content-type".to_string(),
+                "application/x-ndjson".to_string(),
+            )],
+            body: parts.concat(),
+            delay: Duration::ZERO,
+            breaks,
+        }
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+impl Received {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(each, _)| each == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).expect("JSON request body")
+    }
+}
+
+impl MockServer {
+    /// Serves `responses` in order; once they run out, the last one is repeated.
+    pub async fn start(responses: Vec<Canned>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let url = format!("http://{}", listener.local_addr().expect("address"));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = Arc::clone(&requests);
+        tokio::spawn(async move {
+            let mut served = 0;
+            while let Ok((stream, _)) = listener.accept().await {
+                let Some(response) = responses.get(served).or(responses.last()).cloned() else {
+                    return;
+                };
+                served += 1;
+                let received = Arc::clone(&received);
+                tokio::spawn(async move { serve(stream, &response, &received).await });
+            }
+        });
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<Received> {
+        self.requests.lock().expect("lock").clone()
+    }
+}
+
+/// Reads one request, records it and answers it, closing the connection.
+async fn serve(
+    mut stream: TcpStream,
+    response: &Canned,
+    received: &Mutex<Vec<Received>>,
+) -> Option<()> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos;
+        }
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).into_owned();
+    let mut lines = head.lines();
+    let path = lines.next()?.split_whitespace().nth(1)?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    let length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = buffer[head_end + 4..].to_vec();
+    while body.len() < length {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+
+    // Recorded before answering, so the client sees it once it has the reply.
+    received.lock().expect("lock").push(Received {
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    });
+
+    tokio::time::sleep(response.delay).await;
+    let mut reply = format!("HTTP/1.1 {} Mock\r\n", response.status);
+    for (name, value) in &response.headers {
+        reply.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    reply.push_str(&format!(
+        "content-length: {}\r\nconnection: close\r\n\r\n",
+        response.body.len()
+    ));
+    stream.write_all(reply.as_bytes()).await.ok()?;
+    let mut start = 0;
+    for &end in response.breaks.iter().chain([&response.body.len()]) {
+        stream
+            .write_all(&response.body.as_bytes()[start..end])
+            .await
+            .ok()?;
+        stream.flush().await.ok()?;
+        if end < response.body.len() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        start = end;
+    }
+    stream.shutdown().await.ok()
+}
+
+/// HTTP client for tests, bypassing any proxy set in the environment.
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .expect("HTTP client")
+}
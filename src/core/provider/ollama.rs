@@ -0,0 +1,449 @@
+use futures_util::StreamExt;
+use futures_util::future::LocalBoxFuture;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+
+use super::{DeltaSink, FinishReason, Provider, ProviderResult, Reply, Request, check};
+use crate::core::apikey::{ApiKey, KeyError};
+use crate::core::tool::{ToolCall, ToolSpec};
+
+/// Ollama native chat backend (`/api/chat`).
+pub struct Ollama {
+    http: Client,
+    endpoint: String,
+    apikey: Option<ApiKey>,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<FunctionTool<'a>>,
+    // Ollama streams by default, so the flag is always sent.
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<ModelOptions<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a str>,
+    /// JSON Schema the reply must follow.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<&'a serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'static str,
+    content: &'a str,
+    /// Base64 images, without a `data:` prefix.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<FunctionCall>,
+    /// Ollama matches tool results to calls by name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_name: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct FunctionTool<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: &'a ToolSpec,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FunctionCall {
+    function: CallFunction,
+}
+
+/// Unlike OpenAI, arguments are a JSON object rather than text.
+#[derive(Serialize, Deserialize)]
+struct CallFunction {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+#[derive(Default, PartialEq, Serialize)]
+struct ModelOptions<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    /// Ollama's name for `max_tokens`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    message: Option<ChunkMessage>,
+    #[serde(default)]
+    done: bool,
+    /// Why generation stopped, on the final chunk (`stop`, `length`).
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChunkMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<FunctionCall>,
+}
+
+#[derive(Deserialize)]
+struct TagList {
+    models: Vec<TagEntry>,
+}
+
+#[derive(Deserialize)]
+struct TagEntry {
+    name: String,
+}
+
+impl Ollama {
+    pub fn new(http: Client, endpoint: String, apikey: Option<ApiKey>) -> Self {
+        Self {
+            http,
+            endpoint,
+            apikey,
+        }
+    }
+
+    async fn authorize(&self, req: RequestBuilder) -> Result<RequestBuilder, KeyError> {
+        match &self.apikey {
+            Some(key) => {
+                let key = key.value().await?;
+                Ok(req.header("Authorization", format!("Bearer {}", key)))
+            }
+            None => Ok(req),
+        }
+    }
+
+    fn body<'a>(request: &'a Request<'a>, stream: bool) -> ChatRequest<'a> {
+        let params = request.params;
+        let options = ModelOptions {
+            num_ctx: params.num_ctx,
+            temperature: params.temperature,
+            num_predict: params.max_tokens,
+            top_p: params.top_p,
+            stop: params.stop.as_deref(),
+            seed: params.seed,
+            presence_penalty: params.presence_penalty,
+            frequency_penalty: params.frequency_penalty,
+        };
+
+        let messages = request
+            .messages
+            .iter()
+            .map(|message| ChatMessage {
+                role: message.role.as_str(),
+                content: &message.content,
+                images: message
+                    .images
+                    .iter()
+                    .map(|image| image.data.as_str())
+                    .collect(),
+                tool_calls: message
+                    .tool_calls
+                    .iter()
+                    .map(|call| FunctionCall {
+                        function: CallFunction {
+                            name: call.name.clone(),
+                            arguments: call.args().unwrap_or_else(|_| serde_json::json!({})),
+                        },
+                    })
+                    .collect(),
+                tool_name: message.tool_name(request.messages),
+            })
+            .collect();
+        let tools = request
+            .tools
+            .iter()
+            .map(|function| FunctionTool {
+                kind: "function",
+                function,
+            })
+            .collect();
+
+        ChatRequest {
+            model: request.model,
+            messages,
+            tools,
+            stream,
+            options: (options != ModelOptions::default()).then_some(options),
+            keep_alive: params.keep_alive.as_deref(),
+            format: request.schema,
+        }
+    }
+
+    /// Sends the chat request, turning error statuses into `HttpError`.
+    async fn send(&self, request: &Request<'_>, stream: bool) -> ProviderResult<Response> {
+        let body = Self::body(request, stream);
+        let response = self
+            .authorize(self.http.post(&self.endpoint).json(&body))
+            .await?
+            .send()
+            .await?;
+        Ok(check(response).await?)
+    }
+
+    /// Derives the `/api/tags` URL from the chat endpoint.
+    fn tags_url(&self) -> String {
+        let base = self
+            .endpoint
+            .trim_end_matches('/')
+            .trim_end_matches("/api/chat");
+        format!("{}/api/tags", base)
+    }
+}
+
+/// Adds a pull hint when an in-stream error reports the model is not available locally.
+fn describe_error(model: &str, message: &str) -> String {
+    if message.contains("not found") || message.contains("try pulling") {
+        return format!(
+            "ollama: {} (run `ollama pull {}` and retry)",
+            message.trim(),
+            model
+        );
+    }
+    format!("ollama: {}", message.trim())
+}
+
+/// Appends tool calls, numbering them since Ollama has no call ids.
+fn add_calls(request: &Request<'_>, reply: &mut Reply, calls: Vec<FunctionCall>) {
+    for call in calls {
+        reply.tool_calls.push(ToolCall {
+            id: request.call_id(reply.tool_calls.len()),
+            name: call.function.name,
+            arguments: call.function.arguments.to_string(),
+        });
+    }
+}
+
+impl Provider for Ollama {
+    fn complete<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let chunk = self.send(request, false).await?.json::<ChatChunk>().await?;
+            if let Some(error) = chunk.error {
+                return Err(describe_error(request.model, &error).into());
+            }
+            let mut reply = Reply {
+                finish_reason: chunk.done_reason.as_deref().map(FinishReason::parse),
+                ..Reply::default()
+            };
+            if let Some(message) = chunk.message {
+                reply.content = message.content;
+                add_calls(request, &mut reply, message.tool_calls);
+            }
+            Ok(reply)
+        })
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+        on_delta: &'a mut DeltaSink<'_>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let response = self.send(request, true).await?;
+            let mut stream = response.bytes_stream();
+            let mut reply = Reply::default();
+            // Bytes of an unfinished line, kept until its newline arrives.
+            let mut pending: Vec<u8> = Vec::new();
+
+            while let Some(item) = stream.next().await {
+                pending.extend_from_slice(&item?);
+
+                while let Some(pos) = pending.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=pos).collect();
+                    let line = std::str::from_utf8(&line)?.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let chunk: ChatChunk = serde_json::from_str(line)?;
+                    if let Some(error) = chunk.error {
+                        return Err(describe_error(request.model, &error).into());
+                    }
+                    if let Some(message) = chunk.message {
+                        if !message.content.is_empty() {
+                            reply.content.push_str(&message.content);
+                            on_delta(&message.content)?;
+                        }
+                        // Calls arrive whole, in the chunk that completes them.
+                        add_calls(request, &mut reply, message.tool_calls);
+                    }
+                    if chunk.done {
+                        reply.finish_reason = chunk.done_reason.as_deref().map(FinishReason::parse);
+                        return Ok(reply);
+                    }
+                }
+            }
+
+            Ok(reply)
+        })
+    }
+
+    fn list_models(&self) -> LocalBoxFuture<'_, ProviderResult<Vec<String>>> {
+        Box::pin(async move {
+            let req = self.authorize(self.http.get(self.tags_url())).await?;
+            let list = check(req.send().await?).await?.json::<TagList>().await?;
+            Ok(list.models.into_iter().map(|entry| entry.name).collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::super::mock::{Canned, MockServer, client};
+    use super::*;
+    use crate::core::Message;
+    use crate::core::provider::Params;
+
+    fn backend(server: &MockServer) -> Ollama {
+        Ollama::new(client(), format!("{}/api/chat", server.url), None)
+    }
+
+    fn request<'a>(messages: &'a [Message], params: &'a Params) -> Request<'a> {
+        Request {
+            model: "llama-test",
+            messages,
+            params,
+            tools: &[],
+            schema: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn complete_sends_options_keep_alive_and_format() {
+        let server = MockServer::start(vec![Canned::json(
+            200,
+            json!({ "message": { "content": "{\"ok\":true}" }, "done": true, "done_reason": "stop" }),
+        )])
+        .await;
+        let messages = [Message::user("hi")];
+        let params = Params {
+            num_ctx: Some(8192),
+            max_tokens: Some(64),
+            keep_alive: Some("10m".to_string()),
+            ..Params::default()
+        };
+        let schema = json!({ "type": "object" });
+        let request = Request {
+            schema: Some(&schema),
+            ..request(&messages, &params)
+        };
+
+        let reply = backend(&server).complete(&request).await.unwrap();
+
+        assert_eq!(reply.content, "{\"ok\":true}");
+        assert_eq!(reply.finish_reason, Some(FinishReason::Stop));
+        let sent = &server.requests()[0];
+        assert_eq!(sent.path, "/api/chat");
+        let body = sent.json();
+        assert_eq!(body["stream"], false);
+        assert_eq!(
+            body["options"],
+            json!({ "num_ctx": 8192, "num_predict": 64 })
+        );
+        assert_eq!(body["keep_alive"], "10m");
+        assert_eq!(body["format"], schema);
+    }
+
+    #[tokio::test]
+    async fn complete_leaves_out_unset_options() {
+        let server = MockServer::start(vec![Canned::json(
+            200,
+            json!({ "message": { "content": "hi" }, "done": true }),
+        )])
+        .await;
+        let messages = [Message::user("hi")];
+        let params = Params::default();
+
+        backend(&server)
+            .complete(&request(&messages, &params))
+            .await
+            .unwrap();
+
+        let body = server.requests()[0].json();
+        assert_eq!(body.get("options"), None);
+        assert_eq!(body.get("keep_alive"), None);
+        assert_eq!(body.get("format"), None);
+    }
+
+    #[tokio::test]
+    async fn stream_joins_a_line_split_across_chunks() {
+        let server = MockServer::start(vec![Canned::ndjson(&[
+            r#"{"message":{"content":"Hel"#,
+            "lo\"},\"done\":false}\n{\"message\":{\"content\":\"!\"},",
+            "\"done\":true,\"done_reason\":\"length\"}\n",
+        ])])
+        .await;
+        let messages = [Message::user("hi")];
+        let params = Params::default();
+        let mut deltas = Vec::new();
+
+        let reply = backend(&server)
+            .stream(&request(&messages, &params), &mut |delta: &str| {
+                deltas.push(delta.to_string());
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(deltas, ["Hello", "!"]);
+        assert_eq!(reply.content, "Hello!");
+        assert_eq!(reply.finish_reason, Some(FinishReason::Length));
+        assert_eq!(server.requests()[0].json()["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn stream_error_suggests_pulling_the_model() {
+        let server = MockServer::start(vec![Canned::ndjson(&[
+            "{\"message\":{\"content\":\"Hi\"},\"done\":false}\n",
+            "{\"error\":\"model \\\"llama-test\\\" not found, try pulling it first\"}\n",
+        ])])
+        .await;
+        let messages = [Message::user("hi")];
+        let params = Params::default();
+        let mut shown = String::new();
+
+        let err = backend(&server)
+            .stream(&request(&messages, &params), &mut |delta: &str| {
+                shown.push_str(delta);
+                Ok(())
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(shown, "Hi");
+        let message = err.to_string();
+        assert!(message.contains("not found"), "{}", message);
+        assert!(
+            message.contains("run `ollama pull llama-test`"),
+            "{}",
+            message
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use futures_util::future::LocalBoxFuture;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::{DeltaSink, FinishReason, Provider, ProviderResult, Reply, Request, check, sse};
+use crate::core::Message;
+use crate::core::apikey::{ApiKey, KeyError};
+use crate::core::tool::{ToolCall, ToolSpec};
+
+/// OpenAI-compatible chat completions backend.
+pub struct OpenAi {
+    http: Client,
+    endpoint: String,
+    apikey: Option<ApiKey>,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<FunctionTool<'a>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    /// Mistral's name for `seed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    random_seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat<'a>>,
+}
+
+/// Structured output: `{"type": "json_schema", "json_schema": {"name", "schema"}}`.
+#[derive(Serialize)]
+struct ResponseFormat<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    json_schema: JsonSchema<'a>,
+}
+
+#[derive(Serialize)]
+struct JsonSchema<'a> {
+    name: &'static str,
+    schema: &'a serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'static str,
+    content: Content<'a>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<FunctionCall<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<&'a str>,
+}
+
+/// Plain text, or text and images as content parts.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Content<'a> {
+    Text(&'a str),
+    Parts(Vec<ContentPart<'a>>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentPart<'a> {
+    Text { text: &'a str },
+    ImageUrl { image_url: ImageUrl },
+}
+
+/// Images travel inline as `data:` URLs.
+#[derive(Serialize)]
+struct ImageUrl {
+    url: String,
+}
+
+#[derive(Serialize)]
+struct FunctionTool<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: &'a ToolSpec,
+}
+
+#[derive(Serialize)]
+struct FunctionCall<'a> {
+    id: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: CallFunction<'a>,
+}
+
+#[derive(Serialize)]
+struct CallFunction<'a> {
+    name: &'a str,
+    arguments: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    /// Null when the model only calls tools.
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ResponseCall>,
+}
+
+#[derive(Deserialize)]
+struct ResponseCall {
+    #[serde(default)]
+    id: String,
+    function: ResponseFunction,
+}
+
+#[derive(Deserialize)]
+struct ResponseFunction {
+    name: String,
+    #[serde(default)]
+    arguments: String,
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+impl OpenAi {
+    pub fn new(http: Client, endpoint: String, apikey: Option<ApiKey>) -> Self {
+        Self {
+            http,
+            endpoint,
+            apikey,
+        }
+    }
+
+    async fn authorize(&self, req: RequestBuilder) -> Result<RequestBuilder, KeyError> {
+        match &self.apikey {
+            Some(key) => {
+                let key = key.value().await?;
+                Ok(req.header("Authorization", format!("Bearer {}", key)))
+            }
+            None => Ok(req),
+        }
+    }
+
+    fn body<'a>(&self, request: &'a Request<'a>, stream: bool) -> ChatRequest<'a> {
+        let params = request.params;
+        // Mistral (codestral included) rejects `seed` and expects `random_seed`.
+        let mistral = self.endpoint.contains("mistral.ai");
+        let messages = request
+            .messages
+            .iter()
+            .map(|message| ChatMessage {
+                role: message.role.as_str(),
+                content: content(message),
+                tool_calls: message
+                    .tool_calls
+                    .iter()
+                    .map(|call| FunctionCall {
+                        id: &call.id,
+                        kind: "function",
+                        function: CallFunction {
+                            name: &call.name,
+                            arguments: &call.arguments,
+                        },
+                    })
+                    .collect(),
+                tool_call_id: message.tool_call_id.as_deref(),
+            })
+            .collect();
+        let tools = request
+            .tools
+            .iter()
+            .map(|function| FunctionTool {
+                kind: "function",
+                function,
+            })
+            .collect();
+        ChatRequest {
+            model: request.model,
+            messages,
+            tools,
+            stream,
+            temperature: params.temperature,
+            max_tokens: params.max_tokens,
+            top_p: params.top_p,
+            stop: params.stop.as_deref(),
+            seed: params.seed.filter(|_| !mistral),
+            random_seed: params.seed.filter(|_| mistral),
+            presence_penalty: params.presence_penalty,
+            frequency_penalty: params.frequency_penalty,
+            response_format: request.schema.map(|schema| ResponseFormat {
+                kind: "json_schema",
+                json_schema: JsonSchema {
+                    name: "reply",
+                    schema,
+                },
+            }),
+        }
+    }
+
+    /// Derives the `/models` URL from the chat completions endpoint.
+    fn models_url(&self) -> String {
+        let base = self
+            .endpoint
+            .trim_end_matches('/')
+            .trim_end_matches("/chat/completions");
+        format!("{}/models", base)
+    }
+}
+
+/// Keeps the plain string form unless the turn carries images.
+fn content(message: &Message) -> Content<'_> {
+    if message.images.is_empty() {
+        return Content::Text(&message.content);
+    }
+    let mut parts = vec![ContentPart::Text {
+        text: &message.content,
+    }];
+    parts.extend(message.images.iter().map(|image| ContentPart::ImageUrl {
+        image_url: ImageUrl {
+            url: image.data_url(),
+        },
+    }));
+    Content::Parts(parts)
+}
+
+impl Provider for OpenAi {
+    fn complete<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let body = self.body(request, false);
+            let req = self
+                .authorize(self.http.post(&self.endpoint).json(&body))
+                .await?;
+            let response = check(req.send().await?)
+                .await?
+                .json::<ChatResponse>()
+                .await?;
+
+            // Extract the first assistant message from the response.
+            let choice = response
+                .choices
+                .into_iter()
+                .next()
+                .ok_or("No choices returned")?;
+            Ok(Reply {
+                content: choice.message.content.unwrap_or_default(),
+                finish_reason: choice.finish_reason.as_deref().map(FinishReason::parse),
+                tool_calls: choice
+                    .message
+                    .tool_calls
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, call)| ToolCall {
+                        id: if call.id.is_empty() {
+                            request.call_id(index)
+                        } else {
+                            call.id
+                        },
+                        name: call.function.name,
+                        arguments: call.function.arguments,
+                    })
+                    .collect(),
+            })
+        })
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a Request<'a>,
+        on_delta: &'a mut DeltaSink<'_>,
+    ) -> LocalBoxFuture<'a, ProviderResult<Reply>> {
+        Box::pin(async move {
+            let body = self.body(request, true);
+            let req = self
+                .authorize(self.http.post(&self.endpoint).json(&body))
+                .await?;
+            let response = check(req.send().await?).await?;
+            let mut reply = Reply::default();
+
+            sse::read(response, |event| {
+                if event.data.trim() == "[DONE]" {
+                    return Ok(true);
+                }
+                let parsed: serde_json::Value = serde_json::from_str(&event.data)?;
+                if let Some(message) = event.error(&parsed) {
+                    return Err(format!("stream error: {}", message).into());
+                }
+                let choice = &parsed["choices"][0];
+                let delta = choice["delta"]["content"].as_str().unwrap_or("");
+                if !delta.is_empty() {
+                    reply.content.push_str(delta);
+                    on_delta(delta)?;
+                }
+                // Tool calls arrive in pieces keyed by `index`: the id and name first, then
+                // the arguments text in fragments.
+                for part in choice["delta"]["tool_calls"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                {
+                    let index = part["index"].as_u64().unwrap_or(0) as usize;
+                    while reply.tool_calls.len() <= index {
+                        reply.tool_calls.push(ToolCall {
+                            id: String::new(),
+                            name: String::new(),
+                            arguments: String::new(),
+                        });
+                    }
+                    let call = &mut reply.tool_calls[index];
+                    if let Some(id) = part["id"].as_str() {
+                        call.id = id.to_string();
+                    }
+                    if let Some(name) = part["function"]["name"].as_str() {
+                        call.name.push_str(name);
+                    }
+                    if let Some(arguments) = part["function"]["arguments"].as_str() {
+                        call.arguments.push_str(arguments);
+                    }
+                }
+                if let Some(reason) = choice["finish_reason"].as_str() {
+                    reply.finish_reason = Some(FinishReason::parse(reason));
+                }
+                Ok(false)
+            })
+            .await?;
+            // Some compatible servers leave out call ids.
+            for (index, call) in reply.tool_calls.iter_mut().enumerate() {
+                if call.id.is_empty() {
+                    call.id = request.call_id(index);
+                }
+            }
+            Ok(reply)
+        })
+    }
+
+    fn list_models(&self) -> LocalBoxFuture<'_, ProviderResult<Vec<String>>> {
+        Box::pin(async move {
+            let req = self.authorize(self.http.get(self.models_url())).await?;
+            let list = check(req.send().await?).await?.json::<ModelList>().await?;
+            Ok(list.data.into_iter().map(|entry| entry.id).collect())
+        })
+    }
+}
@@ -0,0 +1,237 @@
+use std::str::Utf8Error;
+
+use futures_util::StreamExt;
+use reqwest::Response;
+
+use super::ProviderResult;
+
+/// One dispatched server-sent event.
+#[derive(Debug, Default)]
+pub struct Event {
+    /// Value of the `event:` field; `message` when the server sends none.
+    pub name: String,
+    /// The `data:` lines joined with newlines.
+    pub data: String,
+}
+
+impl Event {
+    /// Whether the event carries an error, by name or by an `error` member in its JSON.
+    pub fn error(&self, data: &serde_json::Value) -> Option<String> {
+        let error = &data["error"];
+        let message = error["message"]
+            .as_str()
+            .or_else(|| error.as_str())
+            .map(str::to_string);
+        match message {
+            Some(message) => Some(message),
+            None if self.name == "error" => Some(
+                data["message"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| self.data.clone()),
+            ),
+            None => None,
+        }
+    }
+}
+
+/// Incremental `text/event-stream` decoder.
+///
+/// Network chunks may end anywhere, even inside a UTF-8 character, so bytes are buffered
+/// until a full line arrives and events are only dispatched on their blank line.
+#[derive(Default)]
+pub struct Decoder {
+    /// Bytes of an unfinished line.
+    pending: Vec<u8>,
+    /// Event being assembled.
+    name: Option<String>,
+    data: Option<String>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a chunk and returns the events it completed.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<Event>, Utf8Error> {
+        self.pending.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let line = std::str::from_utf8(&line)?;
+            let line = line.trim_end_matches('\n').trim_end_matches('\r');
+            if let Some(event) = self.line(line) {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+
+    /// Flushes a final event that the server did not close with a blank line.
+    pub fn finish(&mut self) -> Result<Option<Event>, Utf8Error> {
+        if !self.pending.is_empty() {
+            let rest = std::mem::take(&mut self.pending);
+            let line = std::str::from_utf8(&rest)?
+                .trim_end_matches('\r')
+                .to_string();
+            if let Some(event) = self.line(&line) {
+                return Ok(Some(event));
+            }
+        }
+        Ok(self.line(""))
+    }
+
+    /// Applies one line; a blank line dispatches the pending event, if it has data.
+    fn line(&mut self, line: &str) -> Option<Event> {
+        if line.is_empty() {
+            let name = self.name.take();
+            let data = self.data.take()?;
+            return Some(Event {
+                name: name.unwrap_or_else(|| "message".to_string()),
+                data,
+            });
+        }
+        // Lines starting with a colon are comments, often used as keep-alives.
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.name = Some(value.to_string()),
+            "data" => match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                }
+                None => self.data = Some(value.to_string()),
+            },
+            // `id` and `retry` only matter for reconnecting, which a completion never does.
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Reads `response` as an event stream, passing each event to `on_event` until it returns
+/// `true` or the stream ends.
+pub async fn read(
+    response: Response,
+    mut on_event: impl FnMut(Event) -> ProviderResult<bool>,
+) -> ProviderResult<()> {
+    let mut stream = response.bytes_stream();
+    let mut decoder = Decoder::new();
+    while let Some(item) = stream.next().await {
+        for event in decoder.push(&item?)? {
+            if on_event(event)? {
+                return Ok(());
+            }
+        }
+    }
+    if let Some(event) = decoder.finish()? {
+        on_event(event)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names_and_data(events: &[Event]) -> Vec<(&str, &str)> {
+        events
+            .iter()
+            .map(|event| (event.name.as_str(), event.data.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn dispatches_on_blank_lines() {
+        let mut decoder = Decoder::new();
+        let events = decoder
+            .push(b"event: ping\ndata: {}\n\ndata: one\n\n")
+            .unwrap();
+        assert_eq!(
+            names_and_data(&events),
+            [("ping", "{}"), ("message", "one")]
+        );
+    }
+
+    #[test]
+    fn joins_data_lines_and_skips_comments() {
+        let mut decoder = Decoder::new();
+        let events = decoder
+            .push(b": keep-alive\ndata: a\ndata:b\nid: 7\nretry: 10\n\n")
+            .unwrap();
+        assert_eq!(names_and_data(&events), [("message", "a\nb")]);
+    }
+
+    #[test]
+    fn events_without_data_are_dropped() {
+        let mut decoder = Decoder::new();
+        assert!(decoder.push(b"event: ping\n\n").unwrap().is_empty());
+        // The name does not leak into the next event.
+        let events = decoder.push(b"data: x\n\n").unwrap();
+        assert_eq!(names_and_data(&events), [("message", "x")]);
+    }
+
+    #[test]
+    fn handles_crlf_line_endings() {
+        let mut decoder = Decoder::new();
+        let events = decoder.push(b"event: done\r\ndata: ok\r\n\r\n").unwrap();
+        assert_eq!(names_and_data(&events), [("done", "ok")]);
+    }
+
+    #[test]
+    fn buffers_chunks_split_inside_lines_and_characters() {
+        let stream = "data: caf\u{e9} \u{1f980}\n\n".as_bytes();
+        let mut decoder = Decoder::new();
+        let mut events = Vec::new();
+        for byte in stream.chunks(1) {
+            events.extend(decoder.push(byte).unwrap());
+        }
+        assert_eq!(
+            names_and_data(&events),
+            [("message", "caf\u{e9} \u{1f980}")]
+        );
+    }
+
+    #[test]
+    fn finish_flushes_an_unterminated_event() {
+        let mut decoder = Decoder::new();
+        assert!(decoder.push(b"data: [DONE]").unwrap().is_empty());
+        let event = decoder.finish().unwrap().expect("an event");
+        assert_eq!(
+            (event.name.as_str(), event.data.as_str()),
+            ("message", "[DONE]")
+        );
+        assert!(decoder.finish().unwrap().is_none());
+    }
+
+    #[test]
+    fn error_is_read_from_the_payload_or_the_event_name() {
+        let named = Event {
+            name: "error".to_string(),
+            data: r#"{"message":"overloaded"}"#.to_string(),
+        };
+        let data = serde_json::from_str(&named.data).unwrap();
+        assert_eq!(named.error(&data).as_deref(), Some("overloaded"));
+
+        let nested = Event {
+            name: "message".to_string(),
+            data: r#"{"error":{"message":"bad request"}}"#.to_string(),
+        };
+        let data = serde_json::from_str(&nested.data).unwrap();
+        assert_eq!(nested.error(&data).as_deref(), Some("bad request"));
+
+        let plain = Event {
+            name: "message".to_string(),
+            data: r#"{"text":"hi"}"#.to_string(),
+        };
+        let data = serde_json::from_str(&plain.data).unwrap();
+        assert_eq!(plain.error(&data), None);
+    }
+}
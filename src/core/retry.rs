@@ -0,0 +1,144 @@
+use std::error::Error;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use reqwest::StatusCode;
+
+use crate::core::provider::HttpError;
+
+/// How transient backend failures are retried.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on every further attempt.
+    pub base_delay: Duration,
+    /// Upper bound for a single wait, `Retry-After` included.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns how long to wait before retry number `attempt` (starting at 1), or `None` when
+    /// the error is not transient or the retries are used up.
+    pub fn delay(&self, attempt: u32, err: &(dyn Error + 'static)) -> Option<Duration> {
+        if attempt > self.max_retries {
+            return None;
+        }
+        let retry_after = transient(err)?;
+
+        // A server asking for more than we are willing to wait gets the error back instead.
+        if let Some(wait) = retry_after {
+            return (wait <= self.max_delay).then_some(wait);
+        }
+
+        // Exponential backoff with jitter in [delay / 2, delay].
+        let exp = self
+            .base_delay
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(self.max_delay);
+        Some(exp / 2 + exp.mul_f64(jitter() / 2.0))
+    }
+}
+
+/// Returns `Some(retry_after)` when the error is worth retrying.
+fn transient(err: &(dyn Error + 'static)) -> Option<Option<Duration>> {
+    if let Some(http) = err.downcast_ref::<HttpError>() {
+        let retryable = matches!(
+            http.status,
+            StatusCode::REQUEST_TIMEOUT
+                | StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        ) || http.status.as_u16() == 529; // Anthropic "overloaded"
+        return retryable.then_some(http.retry_after);
+    }
+    if let Some(http) = err.downcast_ref::<reqwest::Error>()
+        && (http.is_connect() || http.is_timeout())
+    {
+        return Some(None);
+    }
+    None
+}
+
+/// Returns a value in `[0, 1)`; precise randomness is not needed to spread retries.
+fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.subsec_nanos())
+        .unwrap_or(0);
+    f64::from(nanos % 1_000_000) / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(code: u16, retry_after: Option<Duration>) -> HttpError {
+        HttpError {
+            status: StatusCode::from_u16(code).unwrap(),
+            retry_after,
+            message: String::new(),
+            code: None,
+            summary: None,
+            hint: None,
+        }
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(300),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter_up_to_the_cap() {
+        let err = status(503, None);
+        let ms = |attempt| policy().delay(attempt, &err).unwrap().as_millis();
+        assert!((50..=100).contains(&ms(1)));
+        assert!((100..=200).contains(&ms(2)));
+        assert!((150..=300).contains(&ms(3)));
+        assert_eq!(policy().delay(4, &err), None);
+    }
+
+    #[test]
+    fn retry_after_is_honored_up_to_max_delay() {
+        let soon = status(429, Some(Duration::from_millis(250)));
+        assert_eq!(policy().delay(1, &soon), Some(Duration::from_millis(250)));
+        let late = status(429, Some(Duration::from_secs(60)));
+        assert_eq!(policy().delay(1, &late), None);
+    }
+
+    #[test]
+    fn only_transient_statuses_are_retried() {
+        for code in [408, 429, 500, 502, 503, 504, 529] {
+            assert!(policy().delay(1, &status(code, None)).is_some(), "{}", code);
+        }
+        for code in [400, 401, 403, 404, 422] {
+            assert_eq!(policy().delay(1, &status(code, None)), None, "{}", code);
+        }
+        let other: Box<dyn Error> = "parse error".into();
+        assert_eq!(policy().delay(1, other.as_ref()), None);
+    }
+
+    #[test]
+    fn zero_retries_disables_retrying() {
+        let policy = RetryPolicy {
+            max_retries: 0,
+            ..policy()
+        };
+        assert_eq!(policy.delay(1, &status(503, None)), None);
+    }
+}
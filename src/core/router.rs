@@ -1,90 +1,777 @@
+use std::io::Write;
+use std::time::Duration;
+
+use futures_util::future::{Either, select};
+
+use crate::core::apikey::ApiKey;
+use crate::core::cache::{self, CachePolicy};
+use crate::core::config::{Source, Task};
+use crate::core::message::format_messages;
+use crate::core::provider::{
+    self, Backend, DeltaSink, HttpError, Params, Provider, Reply, Request,
+};
+use crate::core::retry::RetryPolicy;
+use crate::core::tool::{ToolSpec, Toolbox};
 use crate::core::trace::send_trace;
-use crate::core::{Cli, Config};
+use crate::core::{Cli, Config, Message, NeteroError};
 
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+/// Tool-call round trips allowed before `converse` gives up on a final answer.
+const MAX_TOOL_ROUNDS: usize = 8;
 
+/// Entry point used by tasks to talk to the configured backend.
 pub struct Service {
-    pub http: Client,
-    pub apikey: Option<String>,
-    pub endpoint: String,
+    provider: Box<dyn Provider>,
     pub model: String,
+    pub params: Params,
+    /// Profile the service was built from, if any.
+    pub profile: Option<String>,
+    retry: RetryPolicy,
+    verbose: bool,
+    backend: Backend,
+    endpoint: String,
+    /// Where the API key came from, named in authentication errors.
+    key_source: Option<String>,
+    cache: CachePolicy,
+    /// Services tried in order when this one is unreachable.
+    fallback: Vec<Service>,
+    /// Delay after which the first fallback is raced against this service.
+    hedge_after: Option<Duration>,
+    /// JSON Schema replies must follow, sent to backends that can enforce it.
+    schema: Option<serde_json::Value>,
 }
 
-#[derive(Serialize)]
-pub struct Message {
-    pub role: String,
-    pub content: String,
-}
+impl Service {
+    /// Builds the service for `task`, routed to its `[tasks]` profile when one is mapped.
+    pub fn for_task(config: &Config, args: &Cli, task: Task) -> Result<Self, NeteroError> {
+        let config = config.for_task(args, task).map_err(NeteroError::Config)?;
+        Self::routed(args, task, &config)
+    }
 
-#[derive(Serialize)]
-pub struct ChatRequest {
-    pub model: String,
-    pub messages: Vec<Message>,
-}
+    /// Builds the service for `task` from the given profile, ignoring `[tasks]`.
+    pub fn for_profile(
+        config: &Config,
+        args: &Cli,
+        task: Task,
+        profile: &str,
+    ) -> Result<Self, NeteroError> {
+        let config = config
+            .for_profile(args, task, profile)
+            .map_err(NeteroError::Config)?;
+        Self::routed(args, task, &config)
+    }
 
-#[derive(Deserialize)]
-pub struct ChatResponse {
-    pub choices: Vec<Choice>,
-}
+    /// Builds the service and its fallbacks from a task's resolved config, describing it in
+    /// verbose mode.
+    fn routed(args: &Cli, task: Task, config: &Config) -> Result<Self, NeteroError> {
+        if config.verbose {
+            writeln!(
+                std::io::stdout(),
+                "task: {:#?}\nmodelo: {:#?}\nurl: {:#?}\nbackend: {:#?}\nprofile: {:#?}\nfallback: {:#?}\n",
+                task.as_str(),
+                config.model,
+                config.endpoint,
+                config.backend.as_str(),
+                config.profile.as_deref().unwrap_or("-"),
+                config.fallback.join(", ")
+            ).map_err(NeteroError::output)?;
+        }
 
-#[derive(Deserialize)]
-pub struct Choice {
-    pub message: ResponseMessage,
-}
+        let mut service = Self::new(config)?;
+        for name in &config.fallback {
+            let fallback = config.fallback(args, name).map_err(NeteroError::Config)?;
+            service.fallback.push(Self::new(&fallback)?);
+        }
+        service.hedge_after = config.hedge_after;
+        Ok(service)
+    }
 
-#[derive(Deserialize)]
-pub struct ResponseMessage {
-    pub content: String,
-}
+    /// Builds the service for the resolved config, without fallbacks.
+    pub fn new(config: &Config) -> Result<Self, NeteroError> {
+        Ok(Self {
+            provider: provider::build(config).map_err(NeteroError::Config)?,
+            model: config.model.clone(),
+            params: config.params.clone(),
+            profile: config.profile.clone(),
+            retry: config.retry,
+            verbose: config.verbose,
+            backend: config.backend,
+            endpoint: config.endpoint.clone(),
+            key_source: config
+                .apikey
+                .as_ref()
+                .map(|key| match config.origin("api_key") {
+                    Some(Source::Env(name)) => name.clone(),
+                    Some(Source::Profile(name, _)) => {
+                        let field = match key {
+                            ApiKey::Value(_) => "api_key",
+                            ApiKey::Command(_) => "key_command",
+                            ApiKey::File(_) => "api_key_file",
+                        };
+                        format!("{} of profile {}", field, name)
+                    }
+                    Some(other) => other.to_string(),
+                    None => "API key".to_string(),
+                }),
+            cache: config.cache,
+            fallback: Vec::new(),
+            hedge_after: None,
+            schema: None,
+        })
+    }
 
-impl Service {
-    pub fn new(args: &Cli) -> Self {
-        let config = Config::from_env(args);
+    /// Asks this service and its fallbacks for replies following `schema`.
+    pub fn set_schema(&mut self, schema: Option<serde_json::Value>) {
+        for fallback in &mut self.fallback {
+            fallback.set_schema(schema.clone());
+        }
+        self.schema = schema;
+    }
 
-        if config.verbose {
-            println!("modelo: {:#?}\nurl: {:#?}\n", config.model, config.endpoint);
+    /// Sets a generation parameter from its text form on this service and its fallbacks,
+    /// clearing it when `value` is `None`.
+    pub fn set_param(&mut self, key: &str, value: Option<&str>) -> Result<(), String> {
+        match value {
+            Some(value) => self.params.set(key, value)?,
+            None => self.params.unset(key)?,
         }
+        for fallback in &mut self.fallback {
+            fallback.set_param(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Sends a conversation and returns the first assistant reply, falling back to the next
+    /// provider in the chain when one is unreachable.
+    pub async fn complete(&self, messages: &[Message]) -> Result<String, NeteroError> {
+        Ok(self.ask(messages, &[], None).await?.content)
+    }
+
+    /// Streams a conversation, passing each fragment to `on_delta`, and returns the full reply
+    /// with its finish reason. Falls back like `complete` until the first fragment; hedging does not apply.
+    pub async fn stream(
+        &self,
+        messages: &[Message],
+        on_delta: &mut DeltaSink<'_>,
+    ) -> Result<Reply, NeteroError> {
+        self.ask(messages, &[], Some(on_delta)).await
+    }
 
-        Self {
-            http: Client::new(),
-            apikey: config.apikey,
-            endpoint: config.endpoint,
-            model: config.model,
+    /// Sends a conversation with `tools` available, running the calls the model makes and
+    /// sending their results back until it answers without calling any. The call and result
+    /// turns are appended to `messages`; the final reply is returned for the caller to add.
+    /// Streams when `on_delta` is given.
+    pub async fn converse(
+        &self,
+        messages: &mut Vec<Message>,
+        tools: &dyn Toolbox,
+        mut on_delta: Option<&mut DeltaSink<'_>>,
+    ) -> Result<Reply, NeteroError> {
+        for _ in 0..MAX_TOOL_ROUNDS {
+            let reply = self
+                .ask(messages, tools.specs(), on_delta.as_deref_mut())
+                .await?;
+            if reply.tool_calls.is_empty() {
+                return Ok(reply);
+            }
+            // Keep text streamed before the calls apart from the next round's.
+            if let Some(on_delta) = on_delta.as_deref_mut()
+                && !reply.content.is_empty()
+            {
+                on_delta("\n\n").map_err(NeteroError::output)?;
+            }
+            messages.push(Message::tool_request(
+                reply.content,
+                reply.tool_calls.clone(),
+            ));
+            for call in &reply.tool_calls {
+                let output = tools.call(call).await;
+                let trace = format!("{}({})\n{}", call.name, call.arguments, output);
+                send_trace(":: TOOL ::", &trace).await;
+                messages.push(Message::tool_result(call, output));
+            }
         }
+        Err(NeteroError::Other(format!(
+            "the model was still calling tools after {} rounds",
+            MAX_TOOL_ROUNDS
+        )))
     }
 
-    pub async fn complete(&self, content: &str) -> Result<String, Box<dyn std::error::Error>> {
+    /// Sends one request through the cache and the fallback chain, streaming when `on_delta`
+    /// is given.
+    async fn ask(
+        &self,
+        messages: &[Message],
+        tools: &[ToolSpec],
+        on_delta: Option<&mut DeltaSink<'_>>,
+    ) -> Result<Reply, NeteroError> {
+        let mode = if on_delta.is_some() { " (STREAM)" } else { "" };
         // Send request/response to the trace server when enabled.
-        send_trace(":: REQUEST ::", content).await;
-
-        let body = ChatRequest {
-            model: self.model.clone(),
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: content.to_string(),
-            }],
+        send_trace(
+            &format!(":: REQUEST{} ::", mode),
+            &format_messages(messages),
+        )
+        .await;
+
+        // Tool results depend on local state, so requests offering tools are never cached.
+        let key = if tools.is_empty() {
+            self.cache_key(messages)
+        } else {
+            None
+        };
+        if let Some(content) = self.lookup(key.as_deref()).await {
+            // A cached answer arrives as a single fragment.
+            if let Some(on_delta) = on_delta {
+                on_delta(&content).map_err(NeteroError::output)?;
+            }
+            send_trace(&format!(":: RESPONSE{} ::", mode), &content).await;
+            return Ok(Reply {
+                content,
+                ..Reply::default()
+            });
+        }
+
+        let (reply, answered) = match on_delta {
+            Some(on_delta) => self.stream_routed(messages, tools, on_delta).await?,
+            None => self.complete_routed(messages, tools).await?,
+        };
+        self.record(answered).await;
+        // Replies bound to a schema are cached by the caller once they validate.
+        if self.schema.is_none() {
+            self.remember(key.as_deref(), &reply.content);
+        }
+        send_trace(&format!(":: RESPONSE{} ::", mode), &reply.content).await;
+
+        Ok(reply)
+    }
+
+    /// Walks the fallback chain, hedging first when configured, and returns the reply with
+    /// the service that produced it.
+    async fn complete_routed(
+        &self,
+        messages: &[Message],
+        tools: &[ToolSpec],
+    ) -> Result<(Reply, &Service), NeteroError> {
+        let mut failure = None;
+        let mut next = 0;
+        if let (Some(delay), Some(hedge)) = (self.hedge_after, self.fallback.first()) {
+            match self.complete_hedged(messages, tools, delay, hedge).await {
+                Ok(answer) => return Ok(answer),
+                Err(err) if fails_over(&err) => failure = Some((err, hedge)),
+                Err(err) => return Err(err),
+            }
+            next = 2;
+        }
+        for service in self.chain().skip(next) {
+            if let Some((err, failed)) = &failure {
+                self.fail_over(failed, err, service).await;
+            }
+            match service.complete_once(messages, tools).await {
+                Ok(reply) => return Ok((reply, service)),
+                Err(err) if fails_over(&err) => failure = Some((err, service)),
+                Err(err) => return Err(err),
+            }
+        }
+        Err(failure.map_or_else(
+            || NeteroError::Other("no provider configured".to_string()),
+            |(err, _)| err,
+        ))
+    }
+
+    /// Streams from the first service in the chain that answers; once a fragment went out,
+    /// failures are final.
+    async fn stream_routed(
+        &self,
+        messages: &[Message],
+        tools: &[ToolSpec],
+        on_delta: &mut DeltaSink<'_>,
+    ) -> Result<(Reply, &Service), NeteroError> {
+        let mut failure: Option<(NeteroError, &Service)> = None;
+        for service in self.chain() {
+            if let Some((err, failed)) = &failure {
+                self.fail_over(failed, err, service).await;
+            }
+            let mut emitted = false;
+            match service
+                .stream_once(messages, tools, on_delta, &mut emitted)
+                .await
+            {
+                Ok(reply) => return Ok((reply, service)),
+                Err(err) if !emitted && fails_over(&err) => failure = Some((err, service)),
+                Err(err) => return Err(err),
+            }
+        }
+        Err(failure.map_or_else(
+            || NeteroError::Other("no provider configured".to_string()),
+            |(err, _)| err,
+        ))
+    }
+
+    /// Returns the cache key for the request, or `None` when the cache is off.
+    fn cache_key(&self, messages: &[Message]) -> Option<String> {
+        self.cache.enabled.then(|| {
+            cache::key(
+                self.backend,
+                &self.endpoint,
+                &self.model,
+                &self.params,
+                messages,
+            )
+        })
+    }
+
+    /// Returns a fresh cached answer for `key`, unless `--refresh` asked for a new one.
+    async fn lookup(&self, key: Option<&str>) -> Option<String> {
+        if self.cache.refresh {
+            return None;
+        }
+        let entry = cache::load(key?, self.cache.ttl)?;
+        let notice = format!(
+            "answered from cache ({}, {}s old)",
+            key?,
+            entry.age().as_secs()
+        );
+        self.notify(":: CACHE ::", &notice).await;
+        Some(entry.content)
+    }
+
+    /// Caches `content` as the answer to `messages`, for replies checked by the caller,
+    /// such as structured output that only counts once it follows the schema.
+    pub fn remember_answer(&self, messages: &[Message], content: &str) {
+        self.remember(self.cache_key(messages).as_deref(), content);
+    }
+
+    /// Stores an answer under `key`; a cache that cannot be written only earns a warning.
+    fn remember(&self, key: Option<&str>, content: &str) {
+        let Some(key) = key else {
+            return;
+        };
+        if let Err(err) = cache::store(key, self.backend, &self.model, content) {
+            eprintln!("Warning: cannot write the response cache: {}", err);
+        }
+    }
+
+    /// This service followed by its fallbacks.
+    fn chain(&self) -> impl Iterator<Item = &Service> {
+        std::iter::once(self).chain(&self.fallback)
+    }
+
+    /// Runs the request on this service, racing `hedge` once `delay` passes without an answer.
+    async fn complete_hedged<'a>(
+        &'a self,
+        messages: &[Message],
+        tools: &[ToolSpec],
+        delay: Duration,
+        hedge: &'a Service,
+    ) -> Result<(Reply, &'a Service), NeteroError> {
+        let mut primary = Box::pin(self.complete_once(messages, tools));
+        let first = match tokio::time::timeout(delay, &mut primary).await {
+            Ok(Ok(content)) => return Ok((content, self)),
+            Ok(Err(err)) if fails_over(&err) => err,
+            Ok(Err(err)) => return Err(err),
+            Err(_) => {
+                let notice = format!(
+                    "{} has not answered after {:.1}s; hedging with {}",
+                    self.label(),
+                    delay.as_secs_f64(),
+                    hedge.label()
+                );
+                self.notify(":: HEDGE ::", &notice).await;
+                let backup = Box::pin(hedge.complete_once(messages, tools));
+                return match select(primary, backup).await {
+                    Either::Left((Ok(content), _)) => Ok((content, self)),
+                    Either::Right((Ok(content), _)) => Ok((content, hedge)),
+                    // One side failed; the other may still answer.
+                    Either::Left((Err(_), backup)) => backup.await.map(|content| (content, hedge)),
+                    Either::Right((Err(_), primary)) => {
+                        primary.await.map(|content| (content, self))
+                    }
+                };
+            }
+        };
+
+        // The primary failed before the threshold, so the hedge simply becomes the fallback.
+        self.fail_over(self, &first, hedge).await;
+        let reply = hedge.complete_once(messages, tools).await?;
+        Ok((reply, hedge))
+    }
+
+    /// Sends the request to this service alone, retrying transient failures.
+    async fn complete_once(
+        &self,
+        messages: &[Message],
+        tools: &[ToolSpec],
+    ) -> Result<Reply, NeteroError> {
+        let request = Request {
+            model: &self.model,
+            messages,
+            params: &self.params,
+            tools,
+            schema: self.schema.as_ref(),
+        };
+        let mut attempt = 0;
+        loop {
+            match self.provider.complete(&request).await {
+                Ok(reply) => return Ok(reply),
+                Err(err) => {
+                    attempt += 1;
+                    self.backoff(attempt, err).await?;
+                }
+            }
+        }
+    }
+
+    /// Streams the request from this service alone; `emitted` tells whether any text went out.
+    async fn stream_once(
+        &self,
+        messages: &[Message],
+        tools: &[ToolSpec],
+        on_delta: &mut DeltaSink<'_>,
+        emitted: &mut bool,
+    ) -> Result<Reply, NeteroError> {
+        let request = Request {
+            model: &self.model,
+            messages,
+            params: &self.params,
+            tools,
+            schema: self.schema.as_ref(),
+        };
+        let mut attempt = 0;
+        loop {
+            let result = {
+                let mut sink = |delta: &str| {
+                    *emitted = true;
+                    on_delta(delta)
+                };
+                self.provider.stream(&request, &mut sink).await
+            };
+            match result {
+                Ok(reply) => return Ok(reply),
+                // Text already shown cannot be taken back, so only retry before the first delta.
+                Err(err) if *emitted => return Err(self.explain(err)),
+                Err(err) => {
+                    attempt += 1;
+                    self.backoff(attempt, err).await?;
+                }
+            }
+        }
+    }
+
+    /// Reports the provider that answered in verbose output and the trace.
+    async fn record(&self, service: &Service) {
+        let label = service.label();
+        if self.verbose {
+            eprintln!("answered by {}", label);
+        }
+        send_trace(":: PROVIDER ::", &label).await;
+    }
+
+    /// Reports a switch from `failed` to `next` after `err`.
+    async fn fail_over(&self, failed: &Service, err: &NeteroError, next: &Service) {
+        let notice = format!(
+            "{} failed, falling back to {}: {}",
+            failed.label(),
+            next.label(),
+            err
+        );
+        self.notify(":: FALLBACK ::", &notice).await;
+    }
+
+    /// Shows a notice in verbose mode and sends it to the trace.
+    async fn notify(&self, kind: &str, notice: &str) {
+        if self.verbose {
+            eprintln!("\x1b[33m{}\x1b[0m", notice);
+        }
+        send_trace(kind, notice).await;
+    }
+
+    /// Names the provider: its profile when it has one, then backend and model.
+    fn label(&self) -> String {
+        match &self.profile {
+            Some(profile) => format!(
+                "profile {} ({} {})",
+                profile,
+                self.backend.as_str(),
+                self.model
+            ),
+            None => format!(
+                "{} {} at {}",
+                self.backend.as_str(),
+                self.model,
+                self.endpoint
+            ),
+        }
+    }
+
+    /// Lists the models available on the configured backend.
+    pub async fn list_models(&self) -> Result<Vec<String>, NeteroError> {
+        self.provider
+            .list_models()
+            .await
+            .map_err(|err| self.explain(err))
+    }
+
+    /// Waits before retry number `attempt`, or hands the error back when it should not be retried.
+    async fn backoff(
+        &self,
+        attempt: u32,
+        err: Box<dyn std::error::Error>,
+    ) -> Result<(), NeteroError> {
+        let Some(delay) = self.retry.delay(attempt, err.as_ref()) else {
+            return Err(self.explain(err));
         };
 
-        let mut req = self.http.post(&self.endpoint).json(&body);
+        let notice = format!(
+            "attempt {} failed: {}; retry {}/{} in {:.1}s",
+            attempt,
+            err,
+            attempt,
+            self.retry.max_retries,
+            delay.as_secs_f64()
+        );
+        self.notify(":: RETRY ::", &notice).await;
+
+        tokio::time::sleep(delay).await;
+        Ok(())
+    }
 
-        if let Some(key) = &self.apikey {
-            req = req.header("Authorization", format!("Bearer {}", key));
+    /// Rephrases HTTP errors in terms of this service's configuration and classifies them.
+    fn explain(&self, mut err: Box<dyn std::error::Error>) -> NeteroError {
+        if let Some(http) = err.downcast_mut::<HttpError>() {
+            http.explain(&provider::Context {
+                backend: self.backend,
+                model: &self.model,
+                endpoint: &self.endpoint,
+                key_source: self.key_source.as_deref(),
+            });
         }
+        NeteroError::from_provider(err)
+    }
+}
+
+/// Connection errors, timeouts and 5xx responses move on to the next provider.
+fn fails_over(err: &NeteroError) -> bool {
+    matches!(err, NeteroError::Network(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::core::config;
+    use crate::core::provider::mock::{Canned, MockServer};
+
+    fn reply(text: &str) -> Canned {
+        Canned::json(
+            200,
+            json!({ "choices": [{ "message": { "content": text }, "finish_reason": "stop" }] }),
+        )
+    }
+
+    fn service(server: &MockServer) -> Service {
+        let url = format!("{}/v1/chat/completions", server.url);
+        Service::new(&config::for_endpoint(Backend::OpenAi, &url, "test-model")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        let server = MockServer::start(vec![
+            Canned::json(503, json!({ "error": { "message": "busy" } })),
+            Canned::json(429, json!({ "error": { "message": "slow down" } }))
+                .header("retry-after", "0"),
+            reply("hello"),
+        ])
+        .await;
+
+        let content = service(&server)
+            .complete(&[Message::user("hi")])
+            .await
+            .unwrap();
+
+        assert_eq!(content, "hello");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let server = MockServer::start(vec![
+            Canned::json(400, json!({ "error": { "message": "bad request" } })),
+            reply("unreachable"),
+        ])
+        .await;
+
+        let err = service(&server)
+            .complete(&[Message::user("hi")])
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, NeteroError::Other(_)), "{:?}", err);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let server =
+            MockServer::start(vec![Canned::json(502, json!({ "error": "bad gateway" }))]).await;
+        let mut service = service(&server);
+        service.retry.max_retries = 2;
+
+        let err = service.complete(&[Message::user("hi")]).await.unwrap_err();
 
-        let response = req.send().await?.json::<ChatResponse>().await?;
+        assert!(matches!(err, NeteroError::Network(_)), "{:?}", err);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_is_not_waited_for() {
+        let server = MockServer::start(vec![
+            Canned::json(429, json!({ "error": { "message": "slow down" } }))
+                .header("retry-after", "3600"),
+            reply("unreachable"),
+        ])
+        .await;
+
+        let err = service(&server)
+            .complete(&[Message::user("hi")])
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, NeteroError::RateLimit(_)), "{:?}", err);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    fn failing() -> Canned {
+        Canned::json(503, json!({ "error": { "message": "down" } }))
+    }
+
+    /// `primary` with `fallback` behind it, neither retrying.
+    fn chain(primary: &MockServer, fallback: &MockServer) -> Service {
+        let mut service = service(primary);
+        service.retry.max_retries = 0;
+        let mut fallback = self::service(fallback);
+        fallback.retry.max_retries = 0;
+        service.fallback.push(fallback);
+        service
+    }
+
+    #[tokio::test]
+    async fn set_param_reaches_the_fallbacks() {
+        let primary = MockServer::start(vec![failing()]).await;
+        let fallback = MockServer::start(vec![reply("from fallback")]).await;
+        let mut service = chain(&primary, &fallback);
+
+        service.set_param("temperature", Some("0.2")).unwrap();
+        service.complete(&[Message::user("hi")]).await.unwrap();
+
+        assert_eq!(primary.requests()[0].json()["temperature"], json!(0.2));
+        assert_eq!(fallback.requests()[0].json()["temperature"], json!(0.2));
+
+        service.set_param("temperature", None).unwrap();
+        assert!(service.fallback[0].params.entries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn falls_back_when_unreachable() {
+        let primary = MockServer::start(vec![failing()]).await;
+        let fallback = MockServer::start(vec![reply("from fallback")]).await;
+
+        let content = chain(&primary, &fallback)
+            .complete(&[Message::user("hi")])
+            .await
+            .unwrap();
+
+        assert_eq!(content, "from fallback");
+        assert_eq!(primary.requests().len(), 1);
+        assert_eq!(fallback.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn client_errors_do_not_fall_back() {
+        let primary = MockServer::start(vec![Canned::json(
+            400,
+            json!({ "error": { "message": "bad" } }),
+        )])
+        .await;
+        let fallback = MockServer::start(vec![reply("unreachable")]).await;
+
+        let err = chain(&primary, &fallback)
+            .complete(&[Message::user("hi")])
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, NeteroError::Other(_)), "{:?}", err);
+        assert!(fallback.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_the_last_failure_when_every_provider_fails() {
+        let primary = MockServer::start(vec![failing()]).await;
+        let fallback =
+            MockServer::start(vec![Canned::json(502, json!({ "error": "gateway" }))]).await;
+
+        let err = chain(&primary, &fallback)
+            .complete(&[Message::user("hi")])
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, NeteroError::Network(ref message) if message.contains("502")),
+            "{:?}",
+            err
+        );
+        assert_eq!(fallback.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn stream_falls_back_before_the_first_fragment() {
+        let primary = MockServer::start(vec![failing()]).await;
+        let fallback = MockServer::start(vec![Canned::events(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"from \"}}]}\n\n\
+             data: {\"choices\":[{\"delta\":{\"content\":\"fallback\"},\"finish_reason\":\"stop\"}]}\n\n\
+             data: [DONE]\n\n",
+        )])
+        .await;
+        let mut shown = String::new();
+
+        let reply = chain(&primary, &fallback)
+            .stream(&[Message::user("hi")], &mut |delta: &str| {
+                shown.push_str(delta);
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(reply.content, "from fallback");
+        assert_eq!(shown, "from fallback");
+        assert_eq!(primary.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn hedges_a_slow_provider() {
+        let primary =
+            MockServer::start(vec![reply("too late").delayed(Duration::from_secs(5))]).await;
+        let fallback = MockServer::start(vec![reply("from hedge")]).await;
+        let mut service = chain(&primary, &fallback);
+        service.hedge_after = Some(Duration::from_millis(50));
+
+        let started = std::time::Instant::now();
+        let content = service.complete(&[Message::user("hi")]).await.unwrap();
+
+        assert_eq!(content, "from hedge");
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(fallback.requests().len(), 1);
+    }
 
-        // Extract the first assistant message from the response.
-        let content = response
-            .choices
-            .get(0)
-            .ok_or("No choices returned")?
-            .message
-            .content
-            .clone();
+    #[tokio::test]
+    async fn does_not_hedge_a_fast_provider() {
+        let primary = MockServer::start(vec![reply("from primary")]).await;
+        let fallback = MockServer::start(vec![reply("unreachable")]).await;
+        let mut service = chain(&primary, &fallback);
+        service.hedge_after = Some(Duration::from_secs(5));
 
-        send_trace(":: RESPONSE ::", &content).await;
+        let content = service.complete(&[Message::user("hi")]).await.unwrap();
 
-        Ok(content)
+        assert_eq!(content, "from primary");
+        assert!(fallback.requests().is_empty());
     }
 }
@@ -0,0 +1,409 @@
+use std::cell::RefCell;
+
+use serde_json::{Map, Value};
+
+/// Checks `instance` against a JSON Schema and returns one message per violation, each
+/// starting with the JSON pointer of the offending value.
+///
+/// Covers the keywords structured output relies on: `type`, `enum`, `const`, object and
+/// array shape, length and numeric bounds, the combinators and local `$ref`s. `format`
+/// and `pattern` are not checked.
+pub fn validate(schema: &Value, instance: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    Validator {
+        root: schema,
+        following: RefCell::new(Vec::new()),
+    }
+    .check(schema, instance, "", &mut errors);
+    errors
+}
+
+/// Whether a number satisfies a bound.
+type Bound = fn(f64, f64) -> bool;
+
+struct Validator<'a> {
+    root: &'a Value,
+    /// `$ref`s being followed, with the location they were met at. Meeting one again at
+    /// the same location means the schema loops without consuming the value.
+    following: RefCell<Vec<(String, String)>>,
+}
+
+impl Validator<'_> {
+    fn check(&self, schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+        let schema = match schema {
+            Value::Object(schema) => schema,
+            Value::Bool(false) => return errors.push(format!("{}: no value is allowed", at(path))),
+            _ => return,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let step = (path.to_string(), reference.to_string());
+            match self.resolve(reference) {
+                None => errors.push(format!("{}: cannot resolve $ref {}", at(path), reference)),
+                Some(_) if self.following.borrow().contains(&step) => {
+                    errors.push(format!("{}: $ref {} loops", at(path), reference))
+                }
+                Some(target) => {
+                    self.following.borrow_mut().push(step);
+                    self.check(target, value, path, errors);
+                    self.following.borrow_mut().pop();
+                }
+            }
+        }
+
+        let types: Vec<&str> = match schema.get("type") {
+            Some(Value::String(name)) => vec![name],
+            Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| is_type(value, name)) {
+            // The other keywords would only repeat the mismatch.
+            return errors.push(format!(
+                "{}: expected {}, found {}",
+                at(path),
+                types.join(" or "),
+                type_name(value)
+            ));
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array)
+            && !options.contains(value)
+        {
+            let options = options.iter().map(Value::to_string).collect::<Vec<_>>();
+            errors.push(format!(
+                "{}: must be one of {}",
+                at(path),
+                options.join(", ")
+            ));
+        }
+        if let Some(constant) = schema.get("const")
+            && constant != value
+        {
+            errors.push(format!("{}: must be {}", at(path), constant));
+        }
+
+        match value {
+            Value::Object(object) => self.check_object(schema, object, path, errors),
+            Value::Array(items) => self.check_array(schema, items, path, errors),
+            Value::String(text) => {
+                let length = text.chars().count() as f64;
+                if let Some(min) = number(schema, "minLength")
+                    && length < min
+                {
+                    errors.push(format!("{}: shorter than {} characters", at(path), min));
+                }
+                if let Some(max) = number(schema, "maxLength")
+                    && length > max
+                {
+                    errors.push(format!("{}: longer than {} characters", at(path), max));
+                }
+            }
+            Value::Number(n) => {
+                let n = n.as_f64().unwrap_or(f64::NAN);
+                let bounds: [(&str, Bound, &str); 4] = [
+                    ("minimum", |n, bound| n >= bound, "at least"),
+                    ("maximum", |n, bound| n <= bound, "at most"),
+                    ("exclusiveMinimum", |n, bound| n > bound, "greater than"),
+                    ("exclusiveMaximum", |n, bound| n < bound, "less than"),
+                ];
+                for (keyword, holds, wording) in bounds {
+                    if let Some(bound) = number(schema, keyword)
+                        && !holds(n, bound)
+                    {
+                        errors.push(format!("{}: must be {} {}", at(path), wording, bound));
+                    }
+                }
+                if let Some(step) = number(schema, "multipleOf")
+                    && step > 0.0
+                    && !is_multiple(n, step)
+                {
+                    errors.push(format!("{}: must be a multiple of {}", at(path), step));
+                }
+            }
+            _ => {}
+        }
+
+        let passes = |schema: &Value| self.passes(schema, value, path);
+        for each in list(schema, "allOf") {
+            self.check(each, value, path, errors);
+        }
+        let any_of = list(schema, "anyOf");
+        if !any_of.is_empty() && !any_of.iter().any(&passes) {
+            errors.push(format!("{}: matches none of the `anyOf` schemas", at(path)));
+        }
+        let one_of = list(schema, "oneOf");
+        if !one_of.is_empty() {
+            let matched = one_of.iter().filter(|each| passes(each)).count();
+            if matched != 1 {
+                errors.push(format!(
+                    "{}: matches {} of the `oneOf` schemas instead of exactly one",
+                    at(path),
+                    matched
+                ));
+            }
+        }
+        if let Some(not) = schema.get("not")
+            && passes(not)
+        {
+            errors.push(format!("{}: matches the `not` schema", at(path)));
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &str,
+        errors: &mut Vec<String>,
+    ) {
+        for name in list(schema, "required")
+            .iter()
+            .filter_map(|name| name.as_str())
+        {
+            if !object.contains_key(name) {
+                errors.push(format!(
+                    "{}: missing required property `{}`",
+                    at(path),
+                    name
+                ));
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, value) in object {
+            let child = format!("{}/{}", path, escape(name));
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property) => self.check(property, value, &child, errors),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        errors.push(format!("{}: unexpected property `{}`", at(path), name))
+                    }
+                    Some(extra) => self.check(extra, value, &child, errors),
+                    None => {}
+                },
+            }
+        }
+    }
+
+    fn check_array(
+        &self,
+        schema: &Map<String, Value>,
+        items: &[Value],
+        path: &str,
+        errors: &mut Vec<String>,
+    ) {
+        let count = items.len() as f64;
+        if let Some(min) = number(schema, "minItems")
+            && count < min
+        {
+            errors.push(format!("{}: fewer than {} items", at(path), min));
+        }
+        if let Some(max) = number(schema, "maxItems")
+            && count > max
+        {
+            errors.push(format!("{}: more than {} items", at(path), max));
+        }
+        if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+            let repeated = items
+                .iter()
+                .enumerate()
+                .any(|(index, item)| items[..index].contains(item));
+            if repeated {
+                errors.push(format!("{}: items must be unique", at(path)));
+            }
+        }
+
+        // Older drafts spell `prefixItems` as an `items` array.
+        let (prefix, rest) = match schema.get("items") {
+            Some(Value::Array(prefix)) => (prefix.as_slice(), schema.get("additionalItems")),
+            items => (list(schema, "prefixItems"), items),
+        };
+        for (index, item) in items.iter().enumerate() {
+            let child = format!("{}/{}", path, index);
+            if let Some(item_schema) = prefix.get(index).or(rest) {
+                self.check(item_schema, item, &child, errors);
+            }
+        }
+    }
+
+    fn passes(&self, schema: &Value, value: &Value, path: &str) -> bool {
+        let mut errors = Vec::new();
+        self.check(schema, value, path, &mut errors);
+        errors.is_empty()
+    }
+
+    /// Resolves `#` and `#/json/pointer` references within the schema document.
+    fn resolve(&self, reference: &str) -> Option<&Value> {
+        self.root.pointer(reference.strip_prefix('#')?)
+    }
+}
+
+fn is_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+/// Allows for binary rounding, as in `0.3 / 0.1 == 2.9999999999999996`.
+fn is_multiple(n: f64, step: f64) -> bool {
+    let quotient = n / step;
+    (quotient - quotient.round()).abs() <= 1e-9 * quotient.abs().max(1.0)
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn number(schema: &Map<String, Value>, keyword: &str) -> Option<f64> {
+    schema.get(keyword).and_then(Value::as_f64)
+}
+
+fn list<'a>(schema: &'a Map<String, Value>, keyword: &str) -> &'a [Value] {
+    schema
+        .get(keyword)
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+/// Names the location for messages; the root has an empty pointer.
+fn at(path: &str) -> &str {
+    if path.is_empty() { "(root)" } else { path }
+}
+
+fn escape(name: &str) -> String {
+    name.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn accepts_a_matching_value() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name", "tags"],
+            "additionalProperties": false,
+            "properties": {
+                "name": { "type": "string", "minLength": 1 },
+                "age": { "type": "integer", "minimum": 0 },
+                "tags": { "type": "array", "items": { "enum": ["a", "b"] }, "uniqueItems": true },
+            },
+        });
+        let value = json!({ "name": "Frank", "age": 60, "tags": ["a", "b"] });
+
+        assert_eq!(validate(&schema, &value), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_each_violation_with_its_location() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name"],
+            "additionalProperties": false,
+            "properties": {
+                "age": { "type": "integer", "maximum": 150 },
+                "tags": { "type": "array", "items": { "type": "string" } },
+            },
+        });
+        let value = json!({ "age": 200, "tags": ["ok", 3], "extra": true });
+
+        assert_eq!(
+            validate(&schema, &value),
+            [
+                "(root): missing required property `name`",
+                "/age: must be at most 150",
+                "(root): unexpected property `extra`",
+                "/tags/1: expected string, found number",
+            ]
+        );
+    }
+
+    #[test]
+    fn combinators() {
+        let schema = json!({ "oneOf": [{ "type": "integer" }, { "minimum": 0 }] });
+
+        assert!(validate(&schema, &json!(-1)).is_empty());
+        assert_eq!(
+            validate(&schema, &json!(2)),
+            ["(root): matches 2 of the `oneOf` schemas instead of exactly one"]
+        );
+        assert!(!validate(&json!({ "not": { "type": "null" } }), &Value::Null).is_empty());
+        assert!(!validate(&json!({ "anyOf": [{ "const": 1 }] }), &json!(2)).is_empty());
+    }
+
+    #[test]
+    fn multiple_of_allows_for_rounding() {
+        let schema = json!({ "multipleOf": 0.1 });
+
+        for ok in [json!(0.3), json!(0.7), json!(19.99 * 10.0), json!(0)] {
+            assert!(validate(&schema, &ok).is_empty(), "{}", ok);
+        }
+        assert_eq!(
+            validate(&schema, &json!(0.35)),
+            ["(root): must be a multiple of 0.1"]
+        );
+        assert!(validate(&json!({ "multipleOf": 3 }), &json!(9)).is_empty());
+        assert!(!validate(&json!({ "multipleOf": 3 }), &json!(10)).is_empty());
+    }
+
+    #[test]
+    fn follows_recursive_refs() {
+        let schema = json!({
+            "$ref": "#/$defs/node",
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "value": { "type": "integer" },
+                        "children": { "type": "array", "items": { "$ref": "#/$defs/node" } },
+                    },
+                },
+            },
+        });
+        let tree =
+            json!({ "value": 1, "children": [{ "value": 2, "children": [{ "value": "x" }] }] });
+
+        assert_eq!(
+            validate(&schema, &tree),
+            ["/children/0/children/0/value: expected integer, found string"]
+        );
+        assert_eq!(
+            validate(&json!({ "$ref": "#/missing" }), &json!(1)),
+            ["(root): cannot resolve $ref #/missing"]
+        );
+    }
+
+    #[test]
+    fn ref_loops_are_reported_instead_of_recursing() {
+        let schema = json!({
+            "$ref": "#/$defs/a",
+            "$defs": { "a": { "$ref": "#/$defs/b" }, "b": { "$ref": "#/$defs/a" } },
+        });
+
+        assert_eq!(
+            validate(&schema, &json!(1)),
+            ["(root): $ref #/$defs/a loops"]
+        );
+        assert_eq!(
+            validate(&json!({ "$ref": "#" }), &json!(1)),
+            ["(root): $ref # loops"]
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use futures_util::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+
+/// A function the model may call, with its arguments described by a JSON Schema.
+#[derive(Clone, Debug, Serialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema of the arguments object.
+    pub parameters: serde_json::Value,
+}
+
+/// A call requested by the model.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ToolCall {
+    /// Identifier the result is sent back under; generated when the backend has none.
+    pub id: String,
+    pub name: String,
+    /// Arguments as a JSON object, in text form.
+    pub arguments: String,
+}
+
+impl ToolCall {
+    /// Parses the arguments, treating empty text as an empty object.
+    pub fn args(&self) -> Result<serde_json::Value, String> {
+        if self.arguments.trim().is_empty() {
+            return Ok(serde_json::json!({}));
+        }
+        serde_json::from_str(&self.arguments)
+            .map_err(|err| format!("invalid arguments for {}: {}", self.name, err))
+    }
+}
+
+/// Local actions offered to the model.
+pub trait Toolbox {
+    /// Tools sent with each request.
+    fn specs(&self) -> &[ToolSpec];
+
+    /// Runs a call and returns the text sent back to the model. Failures are returned as
+    /// text too, so the model can correct itself.
+    fn call<'a>(&'a self, call: &'a ToolCall) -> LocalBoxFuture<'a, String>;
+}
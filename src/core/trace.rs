@@ -29,7 +29,7 @@ fn separator_line() -> String {
     ".".repeat(count) + "\n"
 }
 
-pub async fn run_trace_server() -> Result<(), Box<dyn std::error::Error>> {
+pub async fn run_trace_server() -> std::io::Result<()> {
     let socket_path = resolve_trace_socket_path();
 
     // Replace old socket if it exists.
@@ -5,14 +5,32 @@ mod utils;
 use clap::CommandFactory;
 use clap::Parser;
 use clap_complete::generate;
+use core::config::Task;
+use std::io::Write;
+use tasks::cache;
 use tasks::chat;
 use tasks::commit;
+use tasks::models;
 use tasks::pipeline;
+use tasks::settings;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
+    match run().await {
+        Ok(()) => {}
+        // Whoever closed the pipe already has what they wanted.
+        Err(core::NeteroError::BrokenPipe) => std::process::exit(utils::BROKEN_PIPE_EXIT),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(err.exit_code());
+        }
+    }
+}
+
+async fn run() -> Result<(), core::NeteroError> {
     let stdin_is_piped = utils::stdin_is_piped();
-    let stdin = utils::get_stdin();
+    let stdin = utils::get_stdin()
+        .map_err(|err| core::NeteroError::Input(format!("cannot read stdin: {}", err)))?;
     let args = core::Cli::parse();
 
     if args.trace && (args.command.is_some() || !args.prompt.is_empty()) {
@@ -24,24 +42,74 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .exit();
     }
 
+    // Chat and the other subcommands have no single reply to validate.
+    let prompt_mode = match &args.command {
+        None => !args.prompt.is_empty(),
+        Some(core::Commands::Prompt { input }) => !input.is_empty(),
+        Some(_) => false,
+    };
+    if args.schema.is_some() && !prompt_mode {
+        let mut cmd = core::Cli::command();
+        cmd.error(
+            clap::error::ErrorKind::ArgumentConflict,
+            "--schema only applies to prompt mode: netero --schema <FILE> [prompt] <PROMPT>...",
+        )
+        .exit();
+    }
+
     if args.trace {
-        core::trace::run_trace_server().await?;
-        return Ok(());
+        return core::trace::run_trace_server()
+            .await
+            .map_err(core::NeteroError::output);
     }
 
-    let service = core::Service::new(&args);
+    // Works offline and with a broken config unless the models are asked for.
+    if let Some(core::Commands::Completion { shell, with_models }) = &args.command {
+        return print_completion(*shell, *with_models, &args).await;
+    }
 
-    execute(&service, &args, stdin, stdin_is_piped).await?;
+    let config = core::Config::load(&args).map_err(core::NeteroError::Config)?;
+
+    execute(&config, &args, stdin, stdin_is_piped).await?;
 
     Ok(())
 }
 
+/// Prints the completion script; with `with_models`, the provider's models become the
+/// values of `--model` when the config loads and the provider answers in time.
+async fn print_completion(
+    shell: clap_complete::Shell,
+    with_models: bool,
+    args: &core::Cli,
+) -> Result<(), core::NeteroError> {
+    let mut cmd = core::Cli::command();
+    let config = with_models.then(|| core::Config::load(args).ok()).flatten();
+    let ids = match config {
+        Some(config) => models::completion_models(&config).await,
+        None => Vec::new(),
+    };
+    if !ids.is_empty() {
+        cmd = cmd.mut_arg("model", |arg| {
+            arg.value_parser(clap::builder::PossibleValuesParser::new(ids))
+        });
+    }
+    // Written in one go so a closed pipe is an error rather than a panic in clap_complete.
+    let mut script = Vec::new();
+    generate(shell, &mut cmd, "netero", &mut script);
+    std::io::stdout()
+        .write_all(&script)
+        .map_err(core::NeteroError::output)
+}
+
 async fn execute(
-    service: &core::Service,
+    config: &core::Config,
     args: &core::Cli,
     stdin: String,
     stdin_is_piped: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), core::NeteroError> {
+    // Each task talks to the profile routed to it in `[tasks]`.
+    let service = |task| core::Service::for_task(config, args, task);
+
     match &args.command {
         Some(core::Commands::Commit { hint, convention }) => {
             let hint_text = if hint.is_empty() {
@@ -49,26 +117,75 @@ async fn execute(
             } else {
                 Some(hint.join(" "))
             };
-            commit::connect(service, args, hint_text.as_deref(), convention.as_deref()).await?
+            // The flag (or NETERO_CONVENTION) wins over config files.
+            let convention_path = convention
+                .as_deref()
+                .map(std::path::Path::new)
+                .or(config.convention.as_deref());
+            commit::connect(
+                &service(Task::Commit)?,
+                args,
+                config.stream,
+                hint_text.as_deref(),
+                convention_path,
+            )
+            .await?
         }
         Some(core::Commands::Prompt { input }) => {
             let input_text = input.join(" ");
-            pipeline::connect(service, args, &input_text, stdin).await?
+            pipeline::connect(
+                &mut service(Task::Prompt)?,
+                config,
+                args,
+                &input_text,
+                stdin,
+            )
+            .await?
         }
-        Some(core::Commands::Chat) => chat::connect(service, args, stdin, stdin_is_piped).await,
-        Some(core::Commands::Completion { shell }) => {
-            let mut cmd = core::Cli::command();
-            generate(*shell, &mut cmd, "netero", &mut std::io::stdout());
+        Some(core::Commands::Chat) => {
+            let mut services = chat_services(config, args)?;
+            chat::connect(&mut services, config, args, stdin, stdin_is_piped).await?
+        }
+        Some(core::Commands::Config) => settings::show_config(config)?,
+        Some(core::Commands::Models { filter, json }) => {
+            models::list_models(&core::Service::new(config)?, filter.as_deref(), *json).await?
+        }
+        Some(core::Commands::Cache { action }) => match action {
+            Some(core::CacheAction::Prune { all }) => cache::prune_cache(config, *all)?,
+            Some(core::CacheAction::List) | None => cache::list_cache(config)?,
+        },
+        Some(core::Commands::Completion { .. }) => {
+            unreachable!("handled before loading the config")
         }
         None => {
             if args.prompt.is_empty() {
-                chat::connect(service, args, stdin, stdin_is_piped).await;
+                let mut services = chat_services(config, args)?;
+                chat::connect(&mut services, config, args, stdin, stdin_is_piped).await?;
             } else {
                 let prompt_text = args.prompt.join(" ");
-                pipeline::connect(service, args, &prompt_text, stdin).await?;
+                pipeline::connect(
+                    &mut service(Task::Prompt)?,
+                    config,
+                    args,
+                    &prompt_text,
+                    stdin,
+                )
+                .await?;
             }
         }
     }
 
     Ok(())
 }
+
+/// Builds the services used by the chat loop and its `/trans` and `/save` commands.
+fn chat_services(
+    config: &core::Config,
+    args: &core::Cli,
+) -> Result<chat::Services, core::NeteroError> {
+    Ok(chat::Services {
+        chat: core::Service::for_task(config, args, Task::Chat)?,
+        trans: core::Service::for_task(config, args, Task::Trans)?,
+        save: core::Service::for_task(config, args, Task::Save)?,
+    })
+}
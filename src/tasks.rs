@@ -1,11 +1,19 @@
 //! Task modules that power CLI commands.
 /// Shared attachment helpers for tasks.
 pub mod attach;
+/// Response cache listing and pruning.
+pub mod cache;
 /// Interactive chat task.
 pub mod chat;
 /// Commit message generation task.
 pub mod commit;
+/// Model listing and lookup.
+pub mod models;
 /// Single prompt pipeline task.
 pub mod pipeline;
 /// Shared helpers for task output.
 pub mod render;
+/// Effective configuration report.
+pub mod settings;
+/// Built-in tools the model can call.
+pub mod tools;
@@ -1,5 +1,26 @@
 use std::env;
 use std::fs;
+use std::io::Cursor;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+
+use crate::core::message::Image;
+use crate::utils;
+
+/// Extensions attached as images rather than text.
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+/// Largest image file read, in bytes.
+const MAX_IMAGE_FILE: u64 = 20 * 1024 * 1024;
+
+/// Longest side sent to the model, in pixels; larger images are downscaled.
+const MAX_IMAGE_SIDE: u32 = 2048;
+
+/// Largest image sent to the model, in bytes before base64, the lowest provider limit.
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
 
 /// File attachment extracted from user input.
 pub struct Attachment {
@@ -25,8 +46,99 @@ fn expand_path(path: &str) -> String {
     path.to_string()
 }
 
-fn read_file(path: &str) -> Option<String> {
-    fs::read_to_string(path).ok()
+/// Reads `path` the way attachments are read: paths matched by `ignore` are refused,
+/// `~/` is expanded and the file must be UTF-8 text.
+pub fn read_attachment(path: &str, ignore: &[String]) -> Result<Attachment, String> {
+    if utils::is_ignored(path, ignore) {
+        return Err(format!("{} is excluded by the attach ignore list", path));
+    }
+    let expanded = expand_path(path);
+    match fs::metadata(&expanded) {
+        Ok(meta) if meta.is_file() => {}
+        Ok(_) => return Err(format!("{} is not a file", path)),
+        Err(err) => return Err(format!("{}: {}", path, err)),
+    }
+    let content = fs::read_to_string(&expanded).map_err(|err| format!("{}: {}", path, err))?;
+    Ok(Attachment {
+        path: path.to_string(),
+        content,
+    })
+}
+
+/// Returns true if `path` names a PNG, JPEG or WebP file.
+pub fn is_image_path(path: &str) -> bool {
+    path.rsplit_once('.').is_some_and(|(_, extension)| {
+        IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+    })
+}
+
+/// Reads the image at `path` under the attachment rules, downscaling it to
+/// `MAX_IMAGE_SIDE` and re-encoding it when it is too large to send.
+pub fn read_image(path: &str, ignore: &[String]) -> Result<Image, String> {
+    if utils::is_ignored(path, ignore) {
+        return Err(format!("{} is excluded by the attach ignore list", path));
+    }
+    let expanded = expand_path(path);
+    let fail = |message: String| format!("{}: {}", path, message);
+    let meta = fs::metadata(&expanded).map_err(|err| fail(err.to_string()))?;
+    if !meta.is_file() {
+        return Err(format!("{} is not a file", path));
+    }
+    if meta.len() > MAX_IMAGE_FILE {
+        return Err(fail(format!(
+            "image is larger than {} MiB",
+            MAX_IMAGE_FILE / 1024 / 1024
+        )));
+    }
+    let bytes = fs::read(&expanded).map_err(|err| fail(err.to_string()))?;
+    // Trust the contents over the extension.
+    let format = match image::guess_format(&bytes) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP)) => format,
+        _ => return Err(fail("not a PNG, JPEG or WebP image".to_string())),
+    };
+    let (width, height) = image::ImageReader::with_format(Cursor::new(&bytes), format)
+        .into_dimensions()
+        .map_err(|err| fail(err.to_string()))?;
+    if width.max(height) <= MAX_IMAGE_SIDE && bytes.len() <= MAX_IMAGE_BYTES {
+        return Ok(Image {
+            media_type: format.to_mime_type().to_string(),
+            data: STANDARD.encode(&bytes),
+        });
+    }
+
+    let decoded =
+        image::load_from_memory_with_format(&bytes, format).map_err(|err| fail(err.to_string()))?;
+    let (bytes, format) = shrink(decoded).map_err(|err| fail(err.to_string()))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(fail(format!(
+            "image is still larger than {} MiB after downscaling",
+            MAX_IMAGE_BYTES / 1024 / 1024
+        )));
+    }
+    Ok(Image {
+        media_type: format.to_mime_type().to_string(),
+        data: STANDARD.encode(&bytes),
+    })
+}
+
+/// Fits `image` within `MAX_IMAGE_SIDE` and encodes it as JPEG, or as PNG when it has
+/// transparency to keep.
+fn shrink(image: DynamicImage) -> image::ImageResult<(Vec<u8>, ImageFormat)> {
+    let image = if image.width().max(image.height()) > MAX_IMAGE_SIDE {
+        image.resize(MAX_IMAGE_SIDE, MAX_IMAGE_SIDE, FilterType::Triangle)
+    } else {
+        image
+    };
+    let mut out = Cursor::new(Vec::new());
+    let format = if image.color().has_alpha() {
+        image.write_to(&mut out, ImageFormat::Png)?;
+        ImageFormat::Png
+    } else {
+        // The JPEG encoder takes no alpha channel.
+        DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut out, ImageFormat::Jpeg)?;
+        ImageFormat::Jpeg
+    };
+    Ok((out.into_inner(), format))
 }
 
 /// Splits input into tokens, honoring quotes and backslash escapes.
@@ -80,10 +192,17 @@ pub fn split_args(input: &str) -> Vec<String> {
     args
 }
 
-/// Extracts file attachments from tokenized input.
-pub fn extract_attachments_from_tokens(tokens: &[String]) -> (Vec<String>, Vec<Attachment>) {
+/// Extracts file and image attachments from tokenized input, skipping paths matched by
+/// `ignore`.
+///
+/// Images that exist but cannot be sent are reported on stderr and left in the text.
+pub fn extract_attachments_from_tokens(
+    tokens: &[String],
+    ignore: &[String],
+) -> (Vec<String>, Vec<Attachment>, Vec<Image>) {
     let mut remaining = Vec::new();
     let mut attachments = Vec::new();
+    let mut images = Vec::new();
 
     for token in tokens {
         if !is_path_candidate(token) {
@@ -91,30 +210,36 @@ pub fn extract_attachments_from_tokens(tokens: &[String]) -> (Vec<String>, Vec<A
             continue;
         }
 
-        let expanded = expand_path(token);
-        match fs::metadata(&expanded) {
-            Ok(meta) if meta.is_file() => {
-                if let Some(content) = read_file(&expanded) {
-                    attachments.push(Attachment {
-                        path: token.clone(),
-                        content,
-                    });
-                } else {
+        if is_image_path(token) {
+            match read_image(token, ignore) {
+                Ok(image) => images.push(image),
+                Err(err) => {
+                    if fs::metadata(expand_path(token)).is_ok() {
+                        eprintln!("Warning: {}", err);
+                    }
                     remaining.push(token.clone());
                 }
             }
-            _ => remaining.push(token.clone()),
+            continue;
+        }
+
+        match read_attachment(token, ignore) {
+            Ok(attachment) => attachments.push(attachment),
+            Err(_) => remaining.push(token.clone()),
         }
     }
 
-    (remaining, attachments)
+    (remaining, attachments, images)
 }
 
-/// Extracts file attachments from a raw input string.
-pub fn extract_attachments_from_input(input: &str) -> (String, Vec<Attachment>) {
+/// Extracts file and image attachments from a raw input string.
+pub fn extract_attachments_from_input(
+    input: &str,
+    ignore: &[String],
+) -> (String, Vec<Attachment>, Vec<Image>) {
     let tokens = split_args(input);
-    let (_remaining, attachments) = extract_attachments_from_tokens(&tokens);
-    (input.to_string(), attachments)
+    let (_remaining, attachments, images) = extract_attachments_from_tokens(&tokens, ignore);
+    (input.to_string(), attachments, images)
 }
 
 /// Formats attachments into a single block, compatible with stdin attachments.
@@ -0,0 +1,84 @@
+use std::io::Write;
+use std::time::Duration;
+
+use crate::core;
+use crate::core::cache;
+
+/// Characters of each answer shown in the listing.
+const PREVIEW_CHARS: usize = 60;
+
+/// Prints the cache directory and one line per entry: key, age, model and a preview.
+pub fn list_cache(config: &core::Config) -> Result<(), core::NeteroError> {
+    let entries = cache::entries().map_err(cache_error)?;
+    let dir = cache::cache_dir()
+        .map(|dir| dir.display().to_string())
+        .unwrap_or_else(|| "(none)".to_string());
+    let expired = entries
+        .iter()
+        .filter(|(_, entry)| entry.age() > config.cache.ttl)
+        .count();
+    let mut out = std::io::stdout();
+    writeln!(out, "# cache dir: {}", dir).map_err(core::NeteroError::output)?;
+    writeln!(
+        out,
+        "# {} entries, {} expired (ttl {}), cache {}",
+        entries.len(),
+        expired,
+        format_age(config.cache.ttl),
+        if config.cache.enabled { "on" } else { "off" }
+    )
+    .map_err(core::NeteroError::output)?;
+
+    for (key, entry) in &entries {
+        let preview = entry
+            .content
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let preview = match preview.char_indices().nth(PREVIEW_CHARS) {
+            Some((end, _)) => format!("{}...", &preview[..end]),
+            None => preview,
+        };
+        let marker = if entry.age() > config.cache.ttl {
+            " (expired)"
+        } else {
+            ""
+        };
+        writeln!(
+            out,
+            "{}  {:>4}{}  {} {}  {}",
+            &key[..12.min(key.len())],
+            format_age(entry.age()),
+            marker,
+            entry.backend,
+            entry.model,
+            preview
+        )
+        .map_err(core::NeteroError::output)?;
+    }
+    Ok(())
+}
+
+/// Removes expired entries, or all of them with `all`.
+pub fn prune_cache(config: &core::Config, all: bool) -> Result<(), core::NeteroError> {
+    let ttl = (!all).then_some(config.cache.ttl);
+    let removed = cache::prune(ttl).map_err(cache_error)?;
+    let mut out = std::io::stdout();
+    writeln!(out, "removed {} entries", removed).map_err(core::NeteroError::output)?;
+    Ok(())
+}
+
+fn cache_error(err: std::io::Error) -> core::NeteroError {
+    core::NeteroError::Other(format!("response cache: {}", err))
+}
+
+/// Formats a duration with its largest unit (`45s`, `12m`, `3h`, `2d`).
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
@@ -1,4 +1,6 @@
 use crate::core;
+use crate::tasks::attach;
+use crate::tasks::models;
 use crate::tasks::render;
 use crate::utils;
 use rustyline::Context;
@@ -18,11 +20,15 @@ use super::parse::{split_args, strip_inline_commands};
 const HELP_TEXT: &str = "\nCommands:\n\
 /help  Show this help message\n\
 /clean Clear chat history\n\
-/add   Attach file contents to chat context\n\
+/add   Attach files or images to chat context\n\
 /trans Translate text (uses LLM)\n\
 /eval  Evaluate arithmetic expression\n\
 /save  Save an informe about the chat\n\
-/stream [on|off] Toggle streaming output\n";
+/stream [on|off] Toggle streaming output\n\
+/tools [on|off] Let the model call local tools\n\
+/set [KEY [VALUE]] Show, set or clear (no value) a generation parameter, fallbacks included\n\
+/model [ID] List the provider's models or switch to one (fallbacks keep theirs)\n\
+/profile [NAME] List the config profiles or switch to one\n";
 
 /// Provides command name completions for slash-prefixed commands in the prompt.
 pub struct CommandCompleter {
@@ -34,10 +40,14 @@ pub struct CommandCompleter {
     inline_commands: Vec<&'static str>,
     /// Common git subcommands for #!(git ...) completion.
     git_subcommands: Vec<&'static str>,
+    /// Known model IDs for /model completion.
+    models: Vec<String>,
+    /// Config profile names for /profile completion.
+    profiles: Vec<String>,
 }
 
 impl CommandCompleter {
-    pub fn new(commands: Vec<&'static str>) -> Self {
+    pub fn new(commands: Vec<&'static str>, profiles: Vec<String>) -> Self {
         Self {
             commands,
             file_completer: FilenameCompleter::new(),
@@ -48,8 +58,17 @@ impl CommandCompleter {
                 "status", "add", "commit", "push", "pull", "fetch", "log", "diff", "show",
                 "branch", "checkout", "switch", "merge", "rebase", "stash", "reset", "restore",
             ],
+            models: Vec::new(),
+            profiles,
         }
     }
+
+    /// Adds model IDs to the /model suggestions, keeping them sorted and unique.
+    pub fn add_models(&mut self, models: &[String]) {
+        self.models.extend(models.iter().cloned());
+        self.models.sort();
+        self.models.dedup();
+    }
 }
 
 /// Enables rustyline helper integration for slash command completion.
@@ -84,6 +103,26 @@ impl Completer for CommandCompleter {
         if line.starts_with("/add ") && pos >= 5 {
             return self.file_completer.complete(line, pos, ctx);
         }
+        let keys = core::provider::Params::KEYS.map(String::from);
+        for (command, values) in [
+            ("/set ", &keys[..]),
+            ("/model ", &self.models[..]),
+            ("/profile ", &self.profiles[..]),
+        ] {
+            if let Some(value) = line[..pos].strip_prefix(command)
+                && !value.contains(char::is_whitespace)
+            {
+                let matches = values
+                    .iter()
+                    .filter(|name| name.starts_with(value))
+                    .map(|name| Pair {
+                        display: name.clone(),
+                        replacement: name.clone(),
+                    })
+                    .collect();
+                return Ok((command.len(), matches));
+            }
+        }
         let start = line[..pos]
             .rfind(|c: char| c.is_whitespace())
             .map(|idx| idx + 1)
@@ -207,7 +246,7 @@ pub fn handle_help(user_input: &str) -> bool {
     false
 }
 
-pub fn handle_clean(user_input: &str, history: &mut Vec<String>) -> bool {
+pub fn handle_clean(user_input: &str, history: &mut Vec<core::Message>) -> bool {
     if user_input == "/clean" {
         history.clear();
         print!("\x1b[2J\x1b[H");
@@ -234,10 +273,148 @@ pub fn handle_stream(user_input: &str, stream_enabled: &mut bool) -> bool {
     true
 }
 
+pub fn handle_tools(user_input: &str, tools_enabled: &mut bool) -> bool {
+    let Some(rest) = user_input.strip_prefix("/tools") else {
+        return false;
+    };
+    let mode = rest.trim().to_lowercase();
+    if mode == "on" {
+        *tools_enabled = true;
+        println!("\ntools: on");
+    } else if mode == "off" {
+        *tools_enabled = false;
+        println!("\ntools: off");
+    } else {
+        println!("\nUsage: /tools on|off");
+    }
+    true
+}
+
+pub fn handle_set(user_input: &str, service: &mut core::Service) -> bool {
+    let Some(rest) = user_input.strip_prefix("/set") else {
+        return false;
+    };
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return false;
+    }
+
+    let rest = rest.trim();
+    if rest.is_empty() {
+        let entries = service.params.entries();
+        if entries.is_empty() {
+            println!("\nparams: backend defaults");
+        }
+        for (key, value) in entries {
+            println!("\n{} = {}", key, value);
+        }
+        return true;
+    }
+
+    let (key, value) = match rest.split_once(char::is_whitespace) {
+        Some((key, value)) => (key, value.trim()),
+        None => (rest, ""),
+    };
+    let result = if value.is_empty() {
+        service
+            .set_param(key, None)
+            .map(|_| format!("{}: default", key))
+    } else {
+        service
+            .set_param(key, Some(value))
+            .map(|_| format!("{} = {}", key, value))
+    };
+    match result {
+        Ok(message) => println!("\n{}", message),
+        Err(err) => eprintln!("\nError: {}", err),
+    }
+    true
+}
+
+pub async fn handle_model(
+    user_input: &str,
+    service: &mut core::Service,
+    completer: Option<&mut CommandCompleter>,
+) -> Result<bool, String> {
+    let Some(rest) = user_input.strip_prefix("/model") else {
+        return Ok(false);
+    };
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return Ok(false);
+    }
+
+    let id = rest.trim();
+    let listed = models::fetch_models(service).await;
+    if let (Ok(available), Some(completer)) = (&listed, completer) {
+        completer.add_models(available);
+    }
+
+    if id.is_empty() {
+        let available = listed.map_err(|err| format!("AI error: {}", err))?;
+        println!();
+        for model in &available {
+            let marker = if *model == service.model { "*" } else { " " };
+            println!("{} {}", marker, model);
+        }
+        return Ok(true);
+    }
+
+    // Switching never depends on the listing; an unknown ID only earns a warning.
+    if let Ok(available) = &listed
+        && !available.iter().any(|model| model == id)
+    {
+        eprintln!("\nWarning: {} is not in the provider's model list", id);
+    }
+    service.model = id.to_string();
+    println!("\nmodel: {}", id);
+    Ok(true)
+}
+
+pub fn handle_profile(
+    user_input: &str,
+    service: &mut core::Service,
+    config: &core::Config,
+    args: &core::Cli,
+) -> bool {
+    let Some(rest) = user_input.strip_prefix("/profile") else {
+        return false;
+    };
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return false;
+    }
+
+    let name = rest.trim();
+    if name.is_empty() {
+        let names = config.profile_names();
+        if names.is_empty() {
+            println!("\nprofiles: none defined in the config file");
+        } else {
+            println!();
+        }
+        for each in &names {
+            let marker = if service.profile.as_deref() == Some(each) {
+                "*"
+            } else {
+                " "
+            };
+            println!("{} {}", marker, each);
+        }
+        return true;
+    }
+
+    match core::Service::for_profile(config, args, core::config::Task::Chat, name) {
+        Ok(switched) => {
+            *service = switched;
+            println!("\nprofile: {} ({})", name, service.model);
+        }
+        Err(err) => eprintln!("\nError: {}", err),
+    }
+    true
+}
+
 pub fn handle_add(
     user_input: &str,
-    history: &mut Vec<String>,
     pending_stdin: &mut Option<String>,
+    pending_images: &mut Vec<core::message::Image>,
 ) -> bool {
     let Some(rest) = user_input.strip_prefix("/add") else {
         return false;
@@ -250,6 +427,16 @@ pub fn handle_add(
 
     let mut attachment = String::new();
     for path in args {
+        if attach::is_image_path(&path) {
+            match attach::read_image(&path, &[]) {
+                Ok(image) => {
+                    pending_images.push(image);
+                    println!("\nadded image: {}", path);
+                }
+                Err(err) => eprintln!("\nError reading {}", err),
+            }
+            continue;
+        }
         match fs::read_to_string(&path) {
             Ok(content) => {
                 attachment.push_str("\n-- FILE: ");
@@ -257,7 +444,6 @@ pub fn handle_add(
                 attachment.push_str(" --\n");
                 attachment.push_str(&content);
                 attachment.push('\n');
-                history.push(format!("Attachment: {}\n{}\n", path, content));
                 println!("\nadded: {}", path);
             }
             Err(err) => {
@@ -320,26 +506,29 @@ pub async fn handle_trans(
         .unwrap_or_else(|| "auto-detect".to_string());
     let target_lang_name = lang_display_name(&target_lang);
 
-    let prompt = format!(
-        "
-Task: Translate the following text faithfully, preserving its meaning and context.
+    let instruction = format!(
+        "Task: Translate the user's text faithfully, preserving its meaning and context.
 Return only the translation. Do not explain or add anything.
 You must translate. Do not choose any other task or language.
 LANG: {}:{}.
 Source language (locked): {}.
 Target language (locked): {}.
-Target language name (locked): {}.
-
-TEXT:
-{}",
-        source_lang, target_lang, source_lang, target_lang, target_lang_name, text
+Target language name (locked): {}.",
+        source_lang, target_lang, source_lang, target_lang, target_lang_name
     );
+    let messages = [
+        core::Message::system(instruction),
+        core::Message::user(text),
+    ];
 
     if args.verbose {
-        println!("\x1b[32m{}\x1b[0m", prompt);
+        println!(
+            "\x1b[32m{}\x1b[0m",
+            core::message::format_messages(&messages)
+        );
     }
 
-    match service.complete(&prompt).await {
+    match service.complete(&messages).await {
         Ok(text) => {
             let output = render::render_markdown(&text);
             println!("\n{}", output);
@@ -353,7 +542,7 @@ pub async fn handle_save(
     user_input: &str,
     service: &core::Service,
     args: &core::Cli,
-    history: &[String],
+    history: &[core::Message],
 ) -> Result<bool, String> {
     let Some(rest) = user_input.strip_prefix("/save") else {
         return Ok(false);
@@ -362,29 +551,36 @@ pub async fn handle_save(
 
     let datetime = utils::current_datetime();
     let user_lang = utils::get_user_lang();
-    let history_text = history.join("\n");
-    let prompt = if raw_text.is_empty() {
-        format!(
-            "Write an informe for the user.\n\
+    let (instruction, request) = if raw_text.is_empty() {
+        (
+            format!(
+                "Write an informe for the user about the conversation.\n\
 Use the same language as the user.\n\
 User language: {user_lang}\n\
-Do not add footers, notes, or meta commentary.\n\
-Chat history:\n\
-{history_text}\n"
+Do not add footers, notes, or meta commentary."
+            ),
+            "Write the informe about our conversation.".to_string(),
         )
     } else {
-        format!(
-            "Hint (required): {raw_text}\n\
-Chat history:\n\
-{history_text}\n"
+        (
+            format!("Hint (required): {raw_text}\nBase the answer on the conversation."),
+            raw_text,
         )
     };
 
+    let mut messages = Vec::with_capacity(history.len() + 2);
+    messages.push(core::Message::system(instruction));
+    messages.extend(history.iter().cloned());
+    messages.push(core::Message::user(request));
+
     if args.verbose {
-        println!("\x1b[32m{}\x1b[0m", prompt);
+        println!(
+            "\x1b[32m{}\x1b[0m",
+            core::message::format_messages(&messages)
+        );
     }
 
-    let result = match service.complete(&prompt).await {
+    let result = match service.complete(&messages).await {
         Ok(text) => text,
         Err(err) => return Err(format!("AI error: {}", err)),
     };
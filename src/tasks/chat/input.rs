@@ -7,12 +7,17 @@ use std::io::{BufRead, BufReader, Write};
 use super::commands::CommandCompleter;
 
 /// Initializes the line editor with command completion.
-pub fn new_editor() -> Editor<CommandCompleter, DefaultHistory> {
+/// `profiles` are the config profile names offered after `/profile`.
+pub fn new_editor(profiles: Vec<String>) -> Editor<CommandCompleter, DefaultHistory> {
     let mut rl = Editor::<CommandCompleter, DefaultHistory>::new()
         .expect("failed to initialize rustyline editor");
-    rl.set_helper(Some(CommandCompleter::new(vec![
-        "/clean", "/trans", "/eval", "/save", "/help", "/stream", "/add",
-    ])));
+    rl.set_helper(Some(CommandCompleter::new(
+        vec![
+            "/clean", "/trans", "/eval", "/save", "/help", "/stream", "/tools", "/add", "/set",
+            "/model", "/profile",
+        ],
+        profiles,
+    )));
     rl
 }
 
@@ -27,16 +32,17 @@ pub fn open_tty_reader(stdin_is_piped: bool) -> Result<Option<BufReader<File>>,
         .map_err(|err| format!("Error: {}", err))
 }
 
-/// Reads one line of user input from TTY or rustyline.
+/// Reads one line of user input from TTY or rustyline; the prompt shows the active model.
 pub fn read_user_input(
     rl: &mut Editor<CommandCompleter, DefaultHistory>,
     tty_reader: &mut Option<BufReader<File>>,
+    model: &str,
 ) -> Result<Option<String>, String> {
     if let Some(reader) = tty_reader.as_mut() {
         // TTY mode uses a manual prompt to keep colors consistent.
         let mut stdout = std::io::stdout();
         stdout
-            .write_all(b"\x1b[36m\xE2\x9E\x9C ")
+            .write_all(format!("\x1b[36m{} ➜ ", model).as_bytes())
             .map_err(|_| "Error writing prompt".to_string())?;
         stdout
             .flush()
@@ -54,7 +60,7 @@ pub fn read_user_input(
         }
     } else {
         println!("\x1b[36m");
-        let readline = rl.readline("➜ ");
+        let readline = rl.readline(&format!("{} ➜ ", model));
         let user_input = match readline {
             Ok(line) => {
                 // Keep history for Up/Down navigation.
@@ -6,7 +6,6 @@ mod input;
 mod lang;
 mod parse;
 mod prompt;
-mod stream;
 pub mod task;
 
-pub use task::generate_chat as connect;
+pub use task::{Services, generate_chat as connect};
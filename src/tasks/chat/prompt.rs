@@ -1,9 +1,32 @@
-/// Builds the chat prompt body from already-resolved user, datetime, history, and input values.
-pub fn create_prompt(
+/// Default chat role, replaced by the `[chat] persona` setting.
+const DEFAULT_PERSONA: &str = "Conversational terminal assistant";
+
+/// Builds the chat system instruction from already-resolved user, datetime, and language values.
+pub fn create_system_prompt(
+    persona: Option<&str>,
     username: &str,
     datetime: &str,
     user_lang: &str,
-    history: &str,
+) -> String {
+    // NOTE: user_lang should reflect the OS locale (e.g., LANG/LC_ALL).
+    format!(
+        "LLM ROL: {}\nUSERNAME: {}\nDATETIME: {}\nUSER LANG: {}\n\
+:: INSTRUCTION (SYSTEM) ::\n\
+- Keep responses concise: 5-20 lines maximum.\n\
+- Do not use emojis or decorations.\n\
+- Always prioritize the latest user message over earlier turns.\n\
+- The latest message may be completely unrelated to previous messages.\n\
+- Do not assume continuity or context from earlier turns unless the user explicitly refers to it.\n\
+:: END INSTRUCTION (SYSTEM) ::",
+        persona.unwrap_or(DEFAULT_PERSONA),
+        username,
+        datetime,
+        user_lang
+    )
+}
+
+/// Builds the user turn from the input plus optional command output and stdin attachment.
+pub fn create_user_message(
     user_input: &str,
     command_output: Option<&str>,
     stdin_attachment: Option<&str>,
@@ -24,34 +47,19 @@ pub fn create_prompt(
         None => String::new(),
     };
 
-    // NOTE: user_lang should reflect the OS locale (e.g., LANG/LC_ALL).
     let extra_sections = [command_section, stdin_section]
         .into_iter()
         .filter(|section| !section.is_empty())
-        .collect::<Vec<_>>()
-        .join("\n");
-    let extra_block = if extra_sections.is_empty() {
-        String::new()
-    } else {
-        format!("{extra_sections}\n")
-    };
+        .collect::<Vec<_>>();
+
+    // Plain messages go through untouched; markers only wrap mixed content.
+    if extra_sections.is_empty() {
+        return user_input.to_string();
+    }
 
     format!(
-        "LLM ROL: Conversational terminal assistant\nUSERNAME: {}\nDATETIME: {}\nUSER LANG: {}\n\
-:: INSTRUCTION (SYSTEM) ::\n\
-- Keep responses concise: 5-20 lines maximum.\n\
-- Do not use emojis or decorations.\n\
-- Always prioritize the latest user message over the HISTORICAL CHAT.\n\
-- The latest message may be completely unrelated to previous messages.\n\
-- Do not assume continuity or context from the history unless the user explicitly refers to it.\n\
-:: END INSTRUCTION (SYSTEM) ::\n\
-:: HISTORIAL CHAT (SYSTEM) ::\n\
-{}\n\
-:: END HISTORIAL CHAT (SYSTEM) ::\n\
-{}\
-:: USER MESSAGE ::\n\
-{}\n\
-:: END USER MESSAGE ::",
-        username, datetime, user_lang, history, extra_block, user_input
+        "{}\n:: USER MESSAGE ::\n{}\n:: END USER MESSAGE ::",
+        extra_sections.join("\n"),
+        user_input
     )
 }
@@ -1,43 +1,59 @@
+use std::io::Write;
+
 use crate::core;
+use crate::core::tool::Toolbox;
 use crate::tasks::attach;
 use crate::tasks::render;
+use crate::tasks::tools::LocalTools;
 use crate::utils;
 
 use super::commands::{
-    handle_add, handle_clean, handle_eval, handle_help, handle_save, handle_stream, handle_trans,
+    handle_add, handle_clean, handle_eval, handle_help, handle_model, handle_profile, handle_save,
+    handle_set, handle_stream, handle_tools, handle_trans,
 };
 use super::inline_exec::run_inline_commands;
 use super::input::{new_editor, open_tty_reader, read_user_input};
 use super::parse::strip_inline_commands;
-use super::prompt::create_prompt;
-use super::stream::stream_completion;
+use super::prompt::{create_system_prompt, create_user_message};
+
+/// Services used by the chat loop, one per routable task.
+pub struct Services {
+    pub chat: core::Service,
+    pub trans: core::Service,
+    pub save: core::Service,
+}
 
 /// Starts the interactive chat session and handles all supported commands.
 pub async fn generate_chat(
-    service: &core::Service,
+    services: &mut Services,
+    config: &core::Config,
     args: &core::Cli,
     stdin: String,
     stdin_is_piped: bool,
-) {
-    let mut history: Vec<String> = Vec::new();
+) -> Result<(), core::NeteroError> {
+    let mut history: Vec<core::Message> = Vec::new();
     let mut pending_stdin = if stdin.trim().is_empty() {
         None
     } else {
         Some(stdin)
     };
+    let mut pending_images = Vec::new();
     let mut stream_enabled = false;
-    let mut rl = new_editor();
+    let mut tools_enabled = args.tools;
+    let local_tools = LocalTools::new(&config.attach_ignore);
+    let mut rl = new_editor(config.profile_names());
+    if let Some(completer) = rl.helper_mut() {
+        completer.add_models(&config.profile_models());
+        completer.add_models(std::slice::from_ref(&services.chat.model));
+    }
     // When stdin is piped, read user input from TTY so the chat stays interactive.
     let mut tty_reader = match open_tty_reader(stdin_is_piped) {
         Ok(reader) => reader,
-        Err(err) => {
-            eprintln!("{}", err);
-            return;
-        }
+        Err(err) => return Err(core::NeteroError::Input(err)),
     };
 
     loop {
-        let user_input = match read_user_input(&mut rl, &mut tty_reader) {
+        let user_input = match read_user_input(&mut rl, &mut tty_reader, &services.chat.model) {
             Ok(Some(line)) => line,
             Ok(None) => break,
             Err(err) => {
@@ -58,7 +74,7 @@ pub async fn generate_chat(
             continue;
         }
 
-        if handle_add(&user_input, &mut history, &mut pending_stdin) {
+        if handle_add(&user_input, &mut pending_stdin, &mut pending_images) {
             continue;
         }
 
@@ -66,21 +82,45 @@ pub async fn generate_chat(
             continue;
         }
 
-        match handle_trans(&user_input, service, args).await {
+        if handle_tools(&user_input, &mut tools_enabled) {
+            continue;
+        }
+
+        if handle_set(&user_input, &mut services.chat) {
+            continue;
+        }
+
+        match handle_model(&user_input, &mut services.chat, rl.helper_mut()).await {
             Ok(true) => continue,
             Ok(false) => {}
             Err(err) => {
                 eprintln!("{}", err);
-                break;
+                continue;
+            }
+        }
+
+        if handle_profile(&user_input, &mut services.chat, config, args) {
+            if let Some(completer) = rl.helper_mut() {
+                completer.add_models(std::slice::from_ref(&services.chat.model));
             }
+            continue;
         }
 
-        match handle_save(&user_input, service, args, &history).await {
+        match handle_trans(&user_input, &services.trans, args).await {
             Ok(true) => continue,
             Ok(false) => {}
             Err(err) => {
                 eprintln!("{}", err);
-                break;
+                continue;
+            }
+        }
+
+        match handle_save(&user_input, &services.save, args, &history).await {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(err) => {
+                eprintln!("{}", err);
+                continue;
             }
         }
 
@@ -88,11 +128,11 @@ pub async fn generate_chat(
             continue;
         }
 
-        // Build the prompt with history, inline command output, and attachments.
-        let dialog = history.join("\n");
+        // Build the turn with inline command output and attachments.
         let command_output = run_inline_commands(&user_input);
         let cleaned_input = strip_inline_commands(&user_input);
-        let (cleaned_input, attachments) = attach::extract_attachments_from_input(&cleaned_input);
+        let (cleaned_input, attachments, images) =
+            attach::extract_attachments_from_input(&cleaned_input, &config.attach_ignore);
         let attachment_block = attach::format_attachments(&attachments);
         let mut merged_stdin = String::new();
         if let Some(existing) = pending_stdin.as_deref() {
@@ -106,48 +146,76 @@ pub async fn generate_chat(
         } else {
             Some(merged_stdin)
         };
-        let prompt = create_prompt(
-            &utils::get_user(),
-            &utils::current_datetime(),
-            &utils::get_user_lang(),
-            &dialog,
+        let mut turn_images = std::mem::take(&mut pending_images);
+        turn_images.extend(images);
+        let user_message = core::Message::user(create_user_message(
             &cleaned_input,
             command_output.as_deref(),
             merged_stdin.as_deref(),
-        );
+        ))
+        .with_images(turn_images);
         if pending_stdin.is_some() {
             pending_stdin = None;
         }
 
+        let mut messages = Vec::with_capacity(history.len() + 2);
+        messages.push(core::Message::system(create_system_prompt(
+            config.persona.as_deref(),
+            &utils::get_user(),
+            &utils::current_datetime(),
+            &utils::get_user_lang(),
+        )));
+        messages.extend(history.iter().cloned());
+        messages.push(user_message);
+
         if args.verbose {
-            println!("\x1b[32m{}\x1b[0m", prompt);
+            writeln!(
+                std::io::stdout(),
+                "\x1b[32m{}\x1b[0m",
+                core::message::format_messages(&messages)
+            )
+            .map_err(core::NeteroError::output)?;
         }
 
         // Use streaming mode when enabled by the user.
+        // Failed turns are reported and left out of the history.
+        let tools = tools_enabled.then_some(&local_tools as &dyn Toolbox);
         let response = if stream_enabled {
-            match stream_completion(service, &prompt).await {
+            match render::stream_markdown(&services.chat, &mut messages, tools).await {
                 Ok(text) => text,
                 Err(err) => {
-                    eprintln!("AI error: {}", err);
-                    break;
+                    eprintln!("\nAI error: {}", err);
+                    continue;
                 }
             }
         } else {
-            match service.complete(&prompt).await {
+            let reply = match tools {
+                Some(tools) => services
+                    .chat
+                    .converse(&mut messages, tools, None)
+                    .await
+                    .map(|reply| reply.content),
+                None => services.chat.complete(&messages).await,
+            };
+            match reply {
                 Ok(text) => {
                     let output = render::render_markdown(&text);
-                    println!("\n{}", output);
+                    writeln!(std::io::stdout(), "\n{}", output)
+                        .map_err(core::NeteroError::output)?;
                     text
                 }
                 Err(err) => {
                     eprintln!("AI error: {}", err);
-                    break;
+                    continue;
                 }
             }
         };
 
-        // Store the last exchange for future context.
-        history.push(format!("{}: {}", utils::get_user(), cleaned_input));
-        history.push(format!("Assistant: {}\n", response));
+        // Store the last exchange, with any tool calls, for future context.
+        let start = 1 + history.len();
+        history.extend(messages.drain(start..));
+        history.push(core::Message::assistant(response));
     }
+
+    Ok(())
 }
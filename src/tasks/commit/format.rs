@@ -29,3 +29,32 @@ pub fn normalize_commit_message(message: &str) -> String {
     }
     trimmed.to_string()
 }
+
+/// Streaming counterpart of `normalize_commit_message`: inserts the blank line after the
+/// title as fragments pass through.
+#[derive(Default)]
+pub struct TitleGap {
+    /// The title's newline has been seen.
+    titled: bool,
+    /// The line after the title has been checked.
+    checked: bool,
+}
+
+impl TitleGap {
+    pub fn apply(&mut self, delta: &str) -> String {
+        let mut out = String::with_capacity(delta.len() + 1);
+        for ch in delta.chars() {
+            if self.titled && !self.checked {
+                self.checked = true;
+                if ch != '\n' {
+                    out.push('\n');
+                }
+            }
+            if ch == '\n' {
+                self.titled = true;
+            }
+            out.push(ch);
+        }
+        out
+    }
+}
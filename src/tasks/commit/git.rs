@@ -1,5 +1,29 @@
 use std::process::Command;
 
+use crate::core::NeteroError;
+
+/// Fails outside a git work tree or when nothing is staged.
+pub fn ensure_staged() -> Result<(), NeteroError> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .output()
+        .map_err(|err| NeteroError::Git(format!("cannot run git: {}", err)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(NeteroError::Git(stderr.trim().to_string()));
+    }
+
+    let status = Command::new("git")
+        .args(["diff", "--cached", "--quiet"])
+        .status()
+        .map_err(|err| NeteroError::Git(format!("cannot run git: {}", err)))?;
+    match status.code() {
+        Some(0) => Err(NeteroError::Git("no changes staged for commit".to_string())),
+        Some(1) => Ok(()),
+        _ => Err(NeteroError::Git("git diff --cached failed".to_string())),
+    }
+}
+
 /// Collects git status and staged diff to give context to the model.
 pub fn staged_changes() -> String {
     run_commands(&[
@@ -1,25 +1,36 @@
+use std::io::Write;
+
 use crate::core;
 
-use super::format::{comment, cover, normalize_commit_message};
-use super::git::staged_changes;
+use super::format::{TitleGap, comment, cover, normalize_commit_message};
+use super::git::{ensure_staged, staged_changes};
 use super::prompts::{convention, instruction, skeleton};
 
-fn generate(hint: Option<&str>, convention_text: &str) -> String {
-    // Build a single prompt with all required sections.
+fn generate(hint: Option<&str>, convention_text: &str) -> Vec<core::Message> {
+    // Rules go in the system turn, repository data in the user turn.
     let user_hint = hint.unwrap_or("");
 
     let context = "repository context";
     let staged_changes = staged_changes();
 
-    let sections = [
+    let rules = [
         ("INSTRUCTION", instruction().to_string()),
         ("CONVENTION", convention_text.to_string()),
         ("SKELETON", skeleton().to_string()),
+    ];
+    let data = [
         ("PROJECT CONTEXT", context.to_string()),
         ("USER HINT", user_hint.to_string()),
         ("REPOSITORY STATUS", staged_changes),
     ];
 
+    vec![
+        core::Message::system(cover_all(&rules)),
+        core::Message::user(cover_all(&data)),
+    ]
+}
+
+fn cover_all(sections: &[(&str, String)]) -> String {
     sections
         .iter()
         .map(|(title, content)| cover(title, content))
@@ -27,33 +38,76 @@ fn generate(hint: Option<&str>, convention_text: &str) -> String {
         .join("\n\n")
 }
 
-/// Builds a commit prompt, calls the model, and prints the final message.
+/// Builds a commit prompt, calls the model, and prints the final message, as it is
+/// generated when `stream` is set.
 pub async fn generate_commit(
     service: &core::Service,
     args: &core::Cli,
+    stream: bool,
     hint: Option<&str>,
-    convention_path: Option<&str>,
-) -> Result<(), Box<dyn std::error::Error>> {
+    convention_path: Option<&std::path::Path>,
+) -> Result<(), core::NeteroError> {
+    // Fail before reading anything else when there is nothing to describe.
+    ensure_staged()?;
+
     let convention_text = if let Some(path) = convention_path {
-        std::fs::read_to_string(path)?
+        std::fs::read_to_string(path).map_err(|err| {
+            core::NeteroError::Input(format!("convention {}: {}", path.display(), err))
+        })?
     } else {
         convention().to_string()
     };
 
-    let prompt = generate(hint, &convention_text);
+    let messages = generate(hint, &convention_text);
 
+    let mut out = std::io::stdout();
     if args.verbose {
-        println!("{}\n\n", prompt);
+        writeln!(out, "{}\n\n", core::message::format_messages(&messages))
+            .map_err(core::NeteroError::output)?;
+    }
+
+    if stream {
+        let mut gap = TitleGap::default();
+        let reply = service
+            .stream(&messages, &mut |delta: &str| {
+                out.write_all(gap.apply(delta).as_bytes())?;
+                out.flush()
+            })
+            .await?;
+        // Match the spacing of the buffered output, which trims the message first.
+        let trailing = reply
+            .content
+            .chars()
+            .rev()
+            .take_while(|ch| *ch == '\n')
+            .count();
+        writeln!(
+            out,
+            "{}{}",
+            "\n".repeat(3 - trailing.min(3)),
+            comment(&convention_text)
+        )
+        .map_err(core::NeteroError::output)?;
+        if let Some(warning) = reply.finish_reason.and_then(|reason| reason.warning()) {
+            eprintln!("Warning: {}", warning);
+        }
+        return Ok(());
     }
 
-    let result = service.complete(&prompt).await?;
+    let result = service.complete(&messages).await?;
     let result = normalize_commit_message(&result);
 
     // TODO: manejar de forma más segura
     match result.contains("Error: no changes staged for commit") {
-        true => println!("{}", result),
-        false => println!("{}\n\n\n{}", result.trim_end(), comment(&convention_text)),
+        true => writeln!(out, "{}", result),
+        false => writeln!(
+            out,
+            "{}\n\n\n{}",
+            result.trim_end(),
+            comment(&convention_text)
+        ),
     }
+    .map_err(core::NeteroError::output)?;
 
     Ok(())
 }
@@ -0,0 +1,63 @@
+use std::io::Write;
+use std::time::Duration;
+
+use crate::core;
+use crate::utils::glob::glob_match;
+
+/// How long shell completion waits for the provider before leaving models out.
+const COMPLETION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Prints the provider's models, one ID per line or as a JSON array.
+pub async fn list_models(
+    service: &core::Service,
+    filter: Option<&str>,
+    json: bool,
+) -> Result<(), core::NeteroError> {
+    let models = fetch_models(service)
+        .await?
+        .into_iter()
+        .filter(|id| filter.is_none_or(|pattern| matches_filter(pattern, id)))
+        .collect::<Vec<_>>();
+
+    let mut out = std::io::stdout();
+    if json {
+        let output = serde_json::to_string_pretty(&models)
+            .map_err(|err| core::NeteroError::Other(err.to_string()))?;
+        writeln!(out, "{}", output).map_err(core::NeteroError::output)?;
+    } else {
+        for id in &models {
+            writeln!(out, "{}", id).map_err(core::NeteroError::output)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the provider's model IDs, sorted and without duplicates.
+pub async fn fetch_models(service: &core::Service) -> Result<Vec<String>, core::NeteroError> {
+    let mut models = service.list_models().await?;
+    models.sort();
+    models.dedup();
+    Ok(models)
+}
+
+/// Best-effort model list for shell completion; any failure yields an empty list.
+pub async fn completion_models(config: &core::Config) -> Vec<String> {
+    let Ok(service) = core::Service::new(config) else {
+        return Vec::new();
+    };
+    match tokio::time::timeout(COMPLETION_TIMEOUT, fetch_models(&service)).await {
+        Ok(Ok(models)) => models,
+        _ => Vec::new(),
+    }
+}
+
+/// Case-insensitive substring match, or a glob match when the filter has `*` or `?`.
+pub fn matches_filter(filter: &str, id: &str) -> bool {
+    let filter = filter.to_lowercase();
+    let id = id.to_lowercase();
+    if filter.contains(['*', '?']) {
+        glob_match(&filter, &id)
+    } else {
+        id.contains(&filter)
+    }
+}
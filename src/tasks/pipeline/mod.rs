@@ -1,4 +1,5 @@
 //! Single prompt pipeline task.
+mod structured;
 pub mod task;
 
 pub use task::generate_message as connect;
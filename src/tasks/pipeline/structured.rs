@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::core;
+use crate::core::schema;
+use crate::core::tool::Toolbox;
+
+/// Reads the JSON Schema given with `--schema`.
+pub fn load_schema(path: &Path) -> Result<Value, core::NeteroError> {
+    let fail = |message: String| {
+        core::NeteroError::Input(format!("schema {}: {}", path.display(), message))
+    };
+    let text = fs::read_to_string(path).map_err(|err| fail(err.to_string()))?;
+    serde_json::from_str(&text).map_err(|err| fail(format!("invalid JSON: {}", err)))
+}
+
+/// System prompt addition describing the expected reply, for backends without a
+/// structured-output mode and as a hint for the others.
+pub fn instructions(schema: &Value) -> String {
+    let schema = serde_json::to_string_pretty(schema).unwrap_or_default();
+    format!(
+        "Reply with a single JSON value that follows this JSON Schema, without prose or code fences:\n{}",
+        schema
+    )
+}
+
+/// Asks until the reply is JSON that follows `schema`, sending the problems back to the
+/// model up to `retries` times, and returns the parsed value. Only a reply that follows
+/// the schema is cached, as the answer to the original request.
+pub async fn ask_json(
+    service: &core::Service,
+    messages: &mut Vec<core::Message>,
+    schema: &Value,
+    retries: u32,
+    tools: Option<&dyn Toolbox>,
+) -> Result<Value, core::NeteroError> {
+    let request = messages.clone();
+    let mut attempt = 0;
+    loop {
+        let reply = match tools {
+            Some(tools) => service.converse(messages, tools, None).await?.content,
+            None => service.complete(messages).await?,
+        };
+        let problems = match serde_json::from_str::<Value>(unfence(&reply)) {
+            Ok(value) => {
+                let errors = schema::validate(schema, &value);
+                if errors.is_empty() {
+                    // Requests offering tools are never cached.
+                    if tools.is_none() {
+                        service.remember_answer(&request, &reply);
+                    }
+                    return Ok(value);
+                }
+                errors
+            }
+            Err(err) => vec![format!("not valid JSON: {}", err)],
+        };
+        if attempt == retries {
+            return Err(core::NeteroError::Other(format!(
+                "the reply does not follow the schema after {} attempts:\n  {}",
+                retries + 1,
+                problems.join("\n  ")
+            )));
+        }
+        attempt += 1;
+        eprintln!(
+            "Warning: the reply does not follow the schema ({}); asking again ({}/{})",
+            problems[0], attempt, retries
+        );
+        messages.push(core::Message::assistant(reply));
+        messages.push(core::Message::user(format!(
+            "Your reply does not follow the JSON Schema:\n- {}\n\nReply again with only the corrected JSON.",
+            problems.join("\n- ")
+        )));
+    }
+}
+
+/// Strips the code fence models often wrap JSON in, despite being told not to.
+fn unfence(reply: &str) -> &str {
+    let text = reply.trim();
+    let Some(rest) = text.strip_prefix("```") else {
+        return text;
+    };
+    // Skip the language tag on the opening line.
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
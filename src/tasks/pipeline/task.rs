@@ -1,18 +1,26 @@
+use std::io::Write;
+
 use crate::core;
+use crate::core::tool::Toolbox;
 use crate::tasks::attach;
 use crate::tasks::render;
+use crate::tasks::tools::LocalTools;
 use crate::utils;
 
+use super::structured;
+
 pub async fn generate_message(
-    service: &core::Service,
+    service: &mut core::Service,
+    config: &core::Config,
     args: &core::Cli,
     request: &str,
     stdin: String,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), core::NeteroError> {
     let user_lang = utils::get_user_lang();
     let user_lang = utils::normalize_lang_tag(&user_lang);
     let user = utils::get_user();
-    let (cleaned_request, attachments) = attach::extract_attachments_from_input(request);
+    let (cleaned_request, attachments, images) =
+        attach::extract_attachments_from_input(request, &config.attach_ignore);
     let stdin_content = stdin;
     let attached_files = attach::format_attached_files(
         if stdin_content.trim().is_empty() {
@@ -23,9 +31,18 @@ pub async fn generate_message(
         &attachments,
     );
 
+    let schema = args
+        .schema
+        .as_deref()
+        .map(structured::load_schema)
+        .transpose()?;
+    let mut system = format!("USER LANG: {} !important", user_lang);
+    if let Some(schema) = &schema {
+        system.push_str("\n\n");
+        system.push_str(&structured::instructions(schema));
+    }
     let mut prompt = format!(
-        "USER LANG: {} !important\n:: USER HINT ::\n{}\n:: END USER HINT ::",
-        user_lang,
+        ":: USER HINT ::\n{}\n:: END USER HINT ::",
         cleaned_request.trim()
     );
     if let Some(block) = attached_files {
@@ -33,13 +50,71 @@ pub async fn generate_message(
         prompt.push_str(&block);
     }
 
-    let response = service.complete(&prompt).await?;
+    let mut messages = vec![
+        core::Message::system(system),
+        core::Message::user(prompt).with_images(images),
+    ];
+    let tools = args.tools.then(|| LocalTools::new(&config.attach_ignore));
+
+    let mut out = std::io::stdout();
+    // Scripts read stdout, so it gets the JSON alone, never streamed or rendered.
+    if let Some(schema) = schema {
+        if args.verbose {
+            eprintln!("SYSTEM:\n\n{}\n", messages[0].content);
+            eprintln!("{}:\n\n{}\n", user.to_uppercase(), messages[1].content);
+        }
+        service.set_schema(Some(schema.clone()));
+        let tools = tools.as_ref().map(|tools| tools as &dyn Toolbox);
+        let value =
+            structured::ask_json(service, &mut messages, &schema, args.schema_retries, tools)
+                .await?;
+        writeln!(
+            out,
+            "{}",
+            serde_json::to_string_pretty(&value).unwrap_or_default()
+        )
+        .map_err(core::NeteroError::output)?;
+        return Ok(());
+    }
+
+    if config.stream {
+        if args.verbose {
+            writeln!(out, "\x1b[1mSYSTEM:\x1b[0m\n\n{}\n", messages[0].content)
+                .map_err(core::NeteroError::output)?;
+            writeln!(
+                out,
+                "\x1b[1m{}:\x1b[0m\n\n{}\n",
+                user.to_uppercase(),
+                messages[1].content
+            )
+            .map_err(core::NeteroError::output)?;
+            writeln!(out, "\x1b[1mLLM:\x1b[0m\n").map_err(core::NeteroError::output)?;
+        }
+        let tools = tools.as_ref().map(|tools| tools as &dyn Toolbox);
+        render::stream_markdown(service, &mut messages, tools).await?;
+        return Ok(());
+    }
+
+    let response = match &tools {
+        Some(tools) => service.converse(&mut messages, tools, None).await?.content,
+        None => service.complete(&messages).await?,
+    };
 
     if args.verbose {
-        println!("\x1b[1m{}:\x1b[0m\n\n{}\n", user.to_uppercase(), prompt);
-        println!("\x1b[1mLLM:\x1b[0m\n\n{}", response.trim());
+        writeln!(out, "\x1b[1mSYSTEM:\x1b[0m\n\n{}\n", messages[0].content)
+            .map_err(core::NeteroError::output)?;
+        writeln!(
+            out,
+            "\x1b[1m{}:\x1b[0m\n\n{}\n",
+            user.to_uppercase(),
+            messages[1].content
+        )
+        .map_err(core::NeteroError::output)?;
+        writeln!(out, "\x1b[1mLLM:\x1b[0m\n\n{}", response.trim())
+            .map_err(core::NeteroError::output)?;
     } else {
-        println!("{}", render::render_markdown(&response));
+        writeln!(out, "{}", render::render_markdown(&response))
+            .map_err(core::NeteroError::output)?;
     }
 
     Ok(())
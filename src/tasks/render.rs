@@ -1,6 +1,9 @@
-use std::io::IsTerminal;
+use std::io::{IsTerminal, Write};
 use termimad::MadSkin;
 
+use crate::core;
+use crate::core::tool::Toolbox;
+
 /// Renders markdown to terminal-friendly output.
 pub fn render_markdown(response: &str) -> String {
     if !std::io::stdout().is_terminal() {
@@ -9,3 +12,108 @@ pub fn render_markdown(response: &str) -> String {
     let skin = MadSkin::default();
     skin.term_text(response).to_string()
 }
+
+/// Prints a streamed reply as it arrives: raw fragments when stdout is not a terminal,
+/// otherwise markdown rendered one complete line at a time.
+pub struct LiveMarkdown {
+    skin: Option<MadSkin>,
+    /// Text of the line still being received.
+    line: String,
+    /// Inside a fenced code block.
+    code: bool,
+    /// Table rows, held back until the table ends since column widths depend on all rows.
+    table: Vec<String>,
+}
+
+impl LiveMarkdown {
+    pub fn new() -> Self {
+        Self {
+            skin: std::io::stdout().is_terminal().then(MadSkin::default),
+            line: String::new(),
+            code: false,
+            table: Vec::new(),
+        }
+    }
+
+    /// Prints a fragment, or the lines it completes.
+    pub fn push(&mut self, delta: &str) -> std::io::Result<()> {
+        let mut stdout = std::io::stdout();
+        if self.skin.is_none() {
+            stdout.write_all(delta.as_bytes())?;
+            return stdout.flush();
+        }
+        self.line.push_str(delta);
+        while let Some(pos) = self.line.find('\n') {
+            let line: String = self.line.drain(..=pos).collect();
+            self.emit(line.trim_end_matches('\n'))?;
+        }
+        stdout.flush()
+    }
+
+    /// Prints whatever is left once the reply is complete.
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        let line = std::mem::take(&mut self.line);
+        if !line.is_empty() {
+            self.emit(&line)?;
+        }
+        self.flush_table()?;
+        let mut stdout = std::io::stdout();
+        if self.skin.is_none() {
+            stdout.write_all(b"\n")?;
+        }
+        stdout.flush()
+    }
+
+    fn emit(&mut self, line: &str) -> std::io::Result<()> {
+        let Some(skin) = &self.skin else {
+            return Ok(());
+        };
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            self.flush_table()?;
+            self.code = !self.code;
+            return Ok(());
+        }
+        let text = if self.code {
+            skin.term_text(&format!("```\n{}\n```", line)).to_string()
+        } else if trimmed.starts_with('|') {
+            self.table.push(line.to_string());
+            return Ok(());
+        } else if trimmed.is_empty() {
+            "\n".to_string()
+        } else {
+            skin.term_text(line).to_string()
+        };
+        self.flush_table()?;
+        std::io::stdout().write_all(text.as_bytes())
+    }
+
+    fn flush_table(&mut self) -> std::io::Result<()> {
+        let (Some(skin), false) = (&self.skin, self.table.is_empty()) else {
+            return Ok(());
+        };
+        let table = std::mem::take(&mut self.table).join("\n");
+        std::io::stdout().write_all(skin.term_text(&table).to_string().as_bytes())
+    }
+}
+
+/// Streams a reply to stdout through `LiveMarkdown` and returns its text, warning on
+/// stderr when the provider cut it short. With `tools`, the model may call them first and
+/// the call and result turns are added to `messages`.
+pub async fn stream_markdown(
+    service: &core::Service,
+    messages: &mut Vec<core::Message>,
+    tools: Option<&dyn Toolbox>,
+) -> Result<String, core::NeteroError> {
+    let mut live = LiveMarkdown::new();
+    let mut sink = |delta: &str| live.push(delta);
+    let reply = match tools {
+        Some(tools) => service.converse(messages, tools, Some(&mut sink)).await?,
+        None => service.stream(messages, &mut sink).await?,
+    };
+    live.finish().map_err(core::NeteroError::output)?;
+    if let Some(warning) = reply.finish_reason.and_then(|reason| reason.warning()) {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(reply.content)
+}
@@ -0,0 +1,142 @@
+use std::io::Write;
+
+use crate::core;
+use crate::core::config::{PROJECT_FILE, config_path};
+
+/// Prints the effective configuration, one `key = value  # source` line per setting.
+pub fn show_config(config: &core::Config) -> Result<(), core::NeteroError> {
+    let global = match (&config.global, config_path()) {
+        (Some(layer), _) => layer.path.display().to_string(),
+        (None, Some(path)) => format!("{} (not found)", path.display()),
+        (None, None) => "(none)".to_string(),
+    };
+    let project = match &config.project {
+        Some(layer) => layer.path.display().to_string(),
+        None => format!("(no {} found)", PROJECT_FILE),
+    };
+    let mut out = std::io::stdout();
+    writeln!(out, "# global config: {}", global).map_err(core::NeteroError::output)?;
+    writeln!(out, "# project config: {}", project).map_err(core::NeteroError::output)?;
+    writeln!(out).map_err(core::NeteroError::output)?;
+
+    let mut entries: Vec<(String, String)> = vec![
+        ("profile".to_string(), quote_opt(config.profile.as_deref())),
+        ("backend".to_string(), quote(config.backend.as_str())),
+        ("endpoint".to_string(), quote(&config.endpoint)),
+        ("model".to_string(), quote(&config.model)),
+        (
+            "api_key".to_string(),
+            // Never print the secret itself.
+            match &config.apikey {
+                Some(key) => quote(&key.describe()),
+                None => "(unset)".to_string(),
+            },
+        ),
+    ];
+    for (key, value) in config.params.entries() {
+        entries.push((format!("params.{}", key), value.to_string()));
+    }
+    entries.push((
+        "commit.convention".to_string(),
+        quote_opt(
+            config
+                .convention
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .as_deref(),
+        ),
+    ));
+    entries.push((
+        "attach.ignore".to_string(),
+        serde_json::to_string(&config.attach_ignore).unwrap_or_default(),
+    ));
+    entries.push((
+        "chat.persona".to_string(),
+        quote_opt(config.persona.as_deref()),
+    ));
+    entries.push((
+        "retry.max_retries".to_string(),
+        config.retry.max_retries.to_string(),
+    ));
+    entries.push((
+        "retry.base_delay_ms".to_string(),
+        config.retry.base_delay.as_millis().to_string(),
+    ));
+    entries.push((
+        "retry.max_delay_ms".to_string(),
+        config.retry.max_delay.as_millis().to_string(),
+    ));
+    entries.push((
+        "cache.enabled".to_string(),
+        config.cache.enabled.to_string(),
+    ));
+    entries.push((
+        "cache.ttl_secs".to_string(),
+        config.cache.ttl.as_secs().to_string(),
+    ));
+    entries.push(("output.stream".to_string(), config.stream.to_string()));
+    let http = &config.http;
+    let path = |path: &Option<std::path::PathBuf>| {
+        quote_opt(path.as_ref().map(|p| p.display().to_string()).as_deref())
+    };
+    let millis = |delay: Option<std::time::Duration>| {
+        delay
+            .map(|delay| delay.as_millis().to_string())
+            .unwrap_or_else(|| "(unset)".to_string())
+    };
+    entries.push(("http.proxy".to_string(), quote_opt(http.proxy.as_deref())));
+    entries.push((
+        "http.no_proxy".to_string(),
+        quote_opt(http.no_proxy.as_deref()),
+    ));
+    entries.push(("http.ca_bundle".to_string(), path(&http.ca_bundle)));
+    entries.push(("http.client_cert".to_string(), path(&http.client_cert)));
+    entries.push(("http.client_key".to_string(), path(&http.client_key)));
+    entries.push((
+        "http.connect_timeout_ms".to_string(),
+        millis(http.connect_timeout),
+    ));
+    entries.push((
+        "http.read_timeout_ms".to_string(),
+        millis(http.read_timeout),
+    ));
+    entries.push(("http.insecure".to_string(), http.insecure.to_string()));
+    if !config.fallback.is_empty() {
+        entries.push((
+            "fallback".to_string(),
+            serde_json::to_string(&config.fallback).unwrap_or_default(),
+        ));
+    }
+    if let Some(delay) = config.hedge_after {
+        entries.push(("hedge_after_ms".to_string(), delay.as_millis().to_string()));
+    }
+    for (task, profile) in &config.tasks {
+        entries.push((format!("tasks.{}", task.as_str()), quote(profile)));
+    }
+
+    let width = entries.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+    for (key, value) in entries {
+        let source = config
+            .origin(&key)
+            .map(|source| source.to_string())
+            .unwrap_or_else(|| "default".to_string());
+        writeln!(
+            out,
+            "{:width$} = {}  # {}",
+            key,
+            value,
+            source,
+            width = width
+        )
+        .map_err(core::NeteroError::output)?;
+    }
+    Ok(())
+}
+
+fn quote(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn quote_opt(value: Option<&str>) -> String {
+    value.map(quote).unwrap_or_else(|| "(unset)".to_string())
+}
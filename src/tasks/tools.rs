@@ -0,0 +1,427 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use futures_util::future::LocalBoxFuture;
+use serde_json::json;
+
+use crate::core::tool::{ToolCall, ToolSpec, Toolbox};
+use crate::utils;
+
+/// Longest tool output sent back to the model, in bytes.
+const MAX_OUTPUT: usize = 32 * 1024;
+
+/// Most matching lines `grep` reports.
+const MAX_MATCHES: usize = 200;
+
+/// Built-in tools working on the local machine: reading files, listing directories,
+/// searching text and running shell commands once the user approves them.
+pub struct LocalTools {
+    specs: Vec<ToolSpec>,
+    /// Attachment ignore patterns, honored by every tool that touches files.
+    ignore: Vec<String>,
+    /// Canonical working directory; file tools refuse paths resolving outside it.
+    root: Option<PathBuf>,
+}
+
+impl LocalTools {
+    pub fn new(ignore: &[String]) -> Self {
+        let path = |description: &str| json!({ "type": "string", "description": description });
+        let specs = vec![
+            ToolSpec {
+                name: "read_file".to_string(),
+                description: "Read a UTF-8 text file.".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": { "path": path("File path, relative to the working directory") },
+                    "required": ["path"],
+                }),
+            },
+            ToolSpec {
+                name: "list_dir".to_string(),
+                description: "List a directory; subdirectories end with `/`.".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": { "path": path("Directory path; defaults to `.`") },
+                }),
+            },
+            ToolSpec {
+                name: "grep".to_string(),
+                description: "Search files under a path for lines containing a text, \
+                              printing `file:line: text`."
+                    .to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "pattern": { "type": "string", "description": "Text to look for" },
+                        "path": path("File or directory to search; defaults to `.`"),
+                        "ignore_case": { "type": "boolean" },
+                    },
+                    "required": ["pattern"],
+                }),
+            },
+            ToolSpec {
+                name: "run_shell".to_string(),
+                description: "Run a shell command after the user approves it, returning its \
+                              exit status and output."
+                    .to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "command": { "type": "string", "description": "Command line for bash" },
+                    },
+                    "required": ["command"],
+                }),
+            },
+        ];
+        Self {
+            specs,
+            ignore: ignore.to_vec(),
+            root: std::env::current_dir().and_then(fs::canonicalize).ok(),
+        }
+    }
+
+    fn run(&self, call: &ToolCall) -> Result<String, String> {
+        let args = call.args()?;
+        let text = |name: &str| args[name].as_str().map(str::to_string);
+        match call.name.as_str() {
+            "read_file" => {
+                let path = text("path").ok_or("missing `path`")?;
+                let (file, _) = self.resolve(&path)?;
+                if !file.is_file() {
+                    return Err(format!("{} is not a file", path));
+                }
+                fs::read_to_string(&file).map_err(|err| format!("{}: {}", path, err))
+            }
+            "list_dir" => self.list_dir(&text("path").unwrap_or_else(|| ".".to_string())),
+            "grep" => {
+                let pattern = text("pattern").ok_or("missing `pattern`")?;
+                let path = text("path").unwrap_or_else(|| ".".to_string());
+                let ignore_case = args["ignore_case"].as_bool().unwrap_or(false);
+                self.grep(&pattern, &path, ignore_case)
+            }
+            "run_shell" => run_shell(&text("command").ok_or("missing `command`")?),
+            other => Err(format!("unknown tool `{}`", other)),
+        }
+    }
+
+    /// Resolves `path` against the working directory, following `..` and symlinks, and
+    /// returns it with its form relative to the working directory (empty for the directory
+    /// itself). Paths outside the working directory or matched by the ignore list are refused.
+    fn resolve(&self, path: &str) -> Result<(PathBuf, String), String> {
+        let root = self
+            .root
+            .as_deref()
+            .ok_or("the working directory is not available")?;
+        let resolved = root
+            .join(path)
+            .canonicalize()
+            .map_err(|err| format!("{}: {}", path, err))?;
+        let Ok(relative) = resolved.strip_prefix(root) else {
+            return Err(format!("{} is outside the working directory", path));
+        };
+        let relative = relative.to_string_lossy().into_owned();
+        if self.is_ignored(&relative) {
+            return Err(format!("{} is excluded by the attach ignore list", path));
+        }
+        Ok((resolved, relative))
+    }
+
+    /// Returns true when `relative`, or a directory it is in, matches the ignore list.
+    fn is_ignored(&self, relative: &str) -> bool {
+        let mut prefix = String::new();
+        relative.split('/').any(|part| {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(part);
+            !part.is_empty() && utils::is_ignored(&prefix, &self.ignore)
+        })
+    }
+
+    fn list_dir(&self, path: &str) -> Result<String, String> {
+        let (dir, relative) = self.resolve(path)?;
+        let entries = fs::read_dir(&dir).map_err(|err| format!("{}: {}", path, err))?;
+        let mut names = Vec::new();
+        for entry in entries.flatten() {
+            let mut name = entry.file_name().to_string_lossy().into_owned();
+            if self.is_ignored(&join(&relative, &name)) {
+                continue;
+            }
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                name.push('/');
+            }
+            names.push(name);
+        }
+        names.sort();
+        if names.is_empty() {
+            return Ok("<empty>".to_string());
+        }
+        Ok(names.join("\n"))
+    }
+
+    fn grep(&self, pattern: &str, path: &str, ignore_case: bool) -> Result<String, String> {
+        let needle = if ignore_case {
+            pattern.to_lowercase()
+        } else {
+            pattern.to_string()
+        };
+        let mut matches = Vec::new();
+        let mut pending = vec![self.resolve(path)?];
+        while let Some((path, relative)) = pending.pop() {
+            // Symlinks below the starting point are not followed: they can lead out of the
+            // working directory or back up into a loop.
+            let Ok(meta) = fs::symlink_metadata(&path) else {
+                continue;
+            };
+            if meta.is_symlink() {
+                continue;
+            }
+            if meta.is_dir() {
+                let Ok(entries) = fs::read_dir(&path) else {
+                    continue;
+                };
+                let mut children: Vec<(PathBuf, String)> = entries
+                    .flatten()
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    // Hidden entries are mostly VCS and tool state, such as `.git`.
+                    .filter(|name| !name.starts_with('.'))
+                    .map(|name| (path.join(&name), join(&relative, &name)))
+                    .filter(|(_, child)| !self.is_ignored(child))
+                    .collect();
+                children.sort_by(|(_, a), (_, b)| a.cmp(b));
+                pending.extend(children.into_iter().rev());
+                continue;
+            }
+            // Binary and unreadable files are skipped.
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            for (number, line) in content.lines().enumerate() {
+                let found = if ignore_case {
+                    line.to_lowercase().contains(&needle)
+                } else {
+                    line.contains(&needle)
+                };
+                if !found {
+                    continue;
+                }
+                if matches.len() == MAX_MATCHES {
+                    matches.push(format!("[stopped after {} matches]", MAX_MATCHES));
+                    return Ok(matches.join("\n"));
+                }
+                matches.push(format!("{}:{}: {}", relative, number + 1, line.trim_end()));
+            }
+        }
+        if matches.is_empty() {
+            return Ok("<no matches>".to_string());
+        }
+        Ok(matches.join("\n"))
+    }
+}
+
+impl Toolbox for LocalTools {
+    fn specs(&self) -> &[ToolSpec] {
+        &self.specs
+    }
+
+    fn call<'a>(&'a self, call: &'a ToolCall) -> LocalBoxFuture<'a, String> {
+        Box::pin(async move {
+            eprintln!(
+                "\x1b[33m» {} {}\x1b[0m",
+                call.name,
+                escape_controls(&call.arguments)
+            );
+            let output = self
+                .run(call)
+                .unwrap_or_else(|err| format!("error: {}", err));
+            truncate(output)
+        })
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    Path::new(dir).join(name).to_string_lossy().into_owned()
+}
+
+/// Asks on the terminal before running `command`; without a terminal it is refused.
+fn run_shell(command: &str) -> Result<String, String> {
+    let tty = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|_| "denied: there is no terminal to ask the user for approval".to_string())?;
+    let mut prompt = &tty;
+    write!(prompt, "{}", approval_prompt(command)).map_err(|err| err.to_string())?;
+    let mut answer = String::new();
+    BufReader::new(&tty)
+        .read_line(&mut answer)
+        .map_err(|err| err.to_string())?;
+    if !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+        return Err("denied by the user".to_string());
+    }
+
+    let output = Command::new("bash")
+        .args(["-lc", command])
+        .output()
+        .map_err(|err| format!("cannot run bash: {}", err))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Ok(format!(
+        "[exit status]\n{}\n[stdout]\n{}\n[stderr]\n{}",
+        output.status,
+        stdout.trim_end(),
+        stderr.trim_end()
+    ))
+}
+
+/// Asks whether to run `command`, with control characters escaped so a model cannot
+/// rewrite the line (`\x1b[2K\r`) to hide what would actually run.
+fn approval_prompt(command: &str) -> String {
+    format!("\x1b[33mRun `{}`? [y/N] \x1b[0m", escape_controls(command))
+}
+
+/// Escapes control characters, leaving quotes and the rest of `text` as written.
+fn escape_controls(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_debug().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Cuts output to `MAX_OUTPUT` bytes on a character boundary, saying so.
+fn truncate(mut output: String) -> String {
+    if output.len() <= MAX_OUTPUT {
+        return output;
+    }
+    let mut end = MAX_OUTPUT;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    let total = output.len();
+    output.truncate(end);
+    output.push_str(&format!("\n[truncated: {} of {} bytes shown]", end, total));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Working tree for a test:
+    /// `<tmp>/outside.txt`, `<tmp>/tree/{notes.txt, src/lib.rs, secrets/token.txt}`, plus
+    /// `tree/src/loop -> ..` and `tree/escape -> ../outside.txt`.
+    fn tools(name: &str) -> (LocalTools, PathBuf) {
+        let base =
+            std::env::temp_dir().join(format!("netero-tools-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let tree = base.join("tree");
+        fs::create_dir_all(tree.join("src")).unwrap();
+        fs::create_dir_all(tree.join("secrets")).unwrap();
+        fs::write(base.join("outside.txt"), "needle outside\n").unwrap();
+        fs::write(tree.join("notes.txt"), "needle in notes\n").unwrap();
+        fs::write(tree.join("src/lib.rs"), "// needle in lib\n").unwrap();
+        fs::write(tree.join("secrets/token.txt"), "needle secret\n").unwrap();
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("..", tree.join("src/loop")).unwrap();
+            std::os::unix::fs::symlink("../outside.txt", tree.join("escape")).unwrap();
+        }
+        let tools = LocalTools {
+            root: Some(tree.canonicalize().unwrap()),
+            ..LocalTools::new(&["secrets".to_string()])
+        };
+        (tools, base)
+    }
+
+    fn call(tools: &LocalTools, name: &str, args: serde_json::Value) -> Result<String, String> {
+        tools.run(&ToolCall {
+            id: "call".to_string(),
+            name: name.to_string(),
+            arguments: args.to_string(),
+        })
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_working_directory() {
+        let (tools, base) = tools("outside");
+        let outside = base.join("outside.txt").to_string_lossy().into_owned();
+
+        for path in ["../outside.txt", "src/../../outside.txt", outside.as_str()] {
+            let err = call(&tools, "read_file", json!({ "path": path })).unwrap_err();
+            assert!(
+                err.contains("outside the working directory"),
+                "{}: {}",
+                path,
+                err
+            );
+        }
+        assert!(call(&tools, "list_dir", json!({ "path": ".." })).is_err());
+        assert!(call(&tools, "grep", json!({ "pattern": "needle", "path": ".." })).is_err());
+        #[cfg(unix)]
+        assert!(call(&tools, "read_file", json!({ "path": "escape" })).is_err());
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn ignore_list_applies_to_the_normalized_path() {
+        let (tools, base) = tools("ignored");
+
+        for path in [
+            "secrets/token.txt",
+            "./src/../secrets/token.txt",
+            "secrets//token.txt",
+        ] {
+            let err = call(&tools, "read_file", json!({ "path": path })).unwrap_err();
+            assert!(err.contains("ignore list"), "{}: {}", path, err);
+        }
+        assert!(
+            call(
+                &tools,
+                "grep",
+                json!({ "pattern": "needle", "path": "secrets" })
+            )
+            .is_err()
+        );
+        let listing = call(&tools, "list_dir", json!({})).unwrap();
+        assert!(!listing.contains("secrets"), "{}", listing);
+        assert_eq!(
+            call(&tools, "read_file", json!({ "path": "./src/lib.rs" })).unwrap(),
+            "// needle in lib\n"
+        );
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn grep_skips_ignored_paths_and_symlinks() {
+        let (tools, base) = tools("grep");
+
+        let found = call(&tools, "grep", json!({ "pattern": "needle" })).unwrap();
+
+        assert_eq!(
+            found,
+            "notes.txt:1: needle in notes\nsrc/lib.rs:1: // needle in lib"
+        );
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn approval_prompt_escapes_control_characters() {
+        let prompt = approval_prompt("echo 'hi' \"there\"\x1b[2K\rls");
+
+        assert!(
+            prompt.contains("echo 'hi' \"there\"\\u{1b}[2K\\rls"),
+            "{:?}",
+            prompt
+        );
+        // Only the prompt's own colour codes reach the terminal.
+        assert_eq!(prompt.matches('\x1b').count(), 2);
+        assert!(!prompt.contains('\r'));
+    }
+}
@@ -0,0 +1,41 @@
+/// Matches `text` against a shell-style pattern supporting `*` and `?`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it is currently absorbing up to.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+/// Returns true when a path matches any ignore pattern.
+/// Patterns without `/` match the file name, others match the whole path.
+pub fn is_ignored(path: &str, patterns: &[String]) -> bool {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    let name = path.rsplit('/').next().unwrap_or(path);
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+        if pattern.contains('/') {
+            glob_match(pattern, path)
+        } else {
+            glob_match(pattern, name)
+        }
+    })
+}
@@ -1,15 +1,19 @@
 use std::io::{self, IsTerminal, Read};
 
 /// Reads all stdin content when input is piped, otherwise returns empty string.
-pub fn get_stdin() -> String {
-    let mut input = String::new();
+pub fn get_stdin() -> io::Result<String> {
+    let mut input = Vec::new();
 
     if !io::stdin().is_terminal() {
-        io::stdin().read_to_string(&mut input).unwrap();
+        io::stdin().read_to_end(&mut input)?;
     }
-    input
+    String::from_utf8(input)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "input is not valid UTF-8 text"))
 }
 
+/// Exit status for a closed output pipe, the one a SIGPIPE death would give.
+pub const BROKEN_PIPE_EXIT: i32 = 141;
+
 /// Returns true when stdin comes from a pipe.
 pub fn stdin_is_piped() -> bool {
     !io::stdin().is_terminal()